@@ -0,0 +1,203 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Index, LitInt};
+
+/// Derives `open_channel::cereal::CerealBox` for a struct from its list
+/// of fields.
+///
+/// The id of the box is given with a `#[cereal(id = ...)]` attribute and
+/// every field must implement `open_channel::cereal::CerealField`. Fields
+/// are poured out and back in in the order they are declared. A box whose
+/// fields all have a fixed length reports their sum as its
+/// `fixed_len`.
+///
+/// Adding `extensible`, as in `#[cereal(id = 7, extensible)]`, makes the
+/// box extensible, so trailing `Option` fields can be added to it without
+/// breaking older peers. `Option` fields are refused anywhere else, since
+/// without the length of the frame they would read into the next one:
+///
+/// ```compile_fail
+///   use open_channel::cereal::CerealBox;
+///
+///   #[derive(CerealBox, Default)]
+///   #[cereal(id = 20)]
+///   struct Opt {
+///       a: u8,
+///       b: Option<u8>,
+///   }
+/// ```
+///
+/// # Examples
+///
+/// ```
+///   use open_channel::cereal::{CerealBox, CerealStream};
+///
+///   #[derive(CerealBox, Default, PartialEq, Debug)]
+///   #[cereal(id = 5)]
+///   struct AdcQuery {
+///       channel: u8,
+///       length: u8,
+///       increment_usec: u32,
+///   }
+///
+///   let query = AdcQuery { channel: 1, length: 100, increment_usec: 5 };
+///   let mut stream = CerealStream::new();
+///   query.pour_out(&mut stream).unwrap();
+///   assert_eq!(stream.get_vec(), &[1, 100, 5, 0, 0, 0]);
+///
+///   let mut copy = AdcQuery::default();
+///   copy.pour_in(&mut stream).unwrap();
+///   assert_eq!(copy, query);
+///   assert_eq!(copy.get_id(), 5);
+///   assert_eq!(copy.fixed_len(), Some(6));
+/// ```
+#[proc_macro_derive(CerealBox, attributes(cereal))]
+pub fn derive_cereal_box(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let attrs = box_attrs(input)?;
+    let id = attrs.id;
+    let extensible = attrs.extensible;
+
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "CerealBox can only be derived for structs",
+            ))
+        }
+    };
+
+    let types: Vec<&syn::Type> = fields.iter().map(|field| &field.ty).collect();
+    check_optional(&types, extensible)?;
+
+    let members: Vec<TokenStream2> = match fields {
+        Fields::Named(named) => named
+            .named
+            .iter()
+            .map(|field| {
+                let ident = &field.ident;
+                quote!(#ident)
+            })
+            .collect(),
+        Fields::Unnamed(unnamed) => (0..unnamed.unnamed.len())
+            .map(|i| {
+                let index = Index::from(i);
+                quote!(#index)
+            })
+            .collect(),
+        Fields::Unit => Vec::new(),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::open_channel::cereal::CerealBox for #name #ty_generics #where_clause {
+            fn get_id(&self) -> u8 {
+                #id
+            }
+
+            fn pour_out(
+                &self,
+                package: &mut ::open_channel::cereal::CerealStream,
+            ) -> ::std::result::Result<(), ::open_channel::error::CerealError> {
+                #(::open_channel::cereal::CerealField::pour_out_field(&self.#members, package)?;)*
+                ::std::result::Result::Ok(())
+            }
+
+            fn extensible(&self) -> bool {
+                #extensible
+            }
+
+            fn fixed_len(&self) -> ::std::option::Option<usize> {
+                #[allow(unused_mut)]
+                let mut len = 0;
+                #(len += <#types as ::open_channel::cereal::CerealField>::FIXED_LEN?;)*
+                ::std::option::Option::Some(len)
+            }
+
+            fn pour_in(
+                &mut self,
+                package: &mut dyn ::open_channel::cereal::CerealRead,
+            ) -> ::std::result::Result<(), ::open_channel::error::CerealError> {
+                #(self.#members = ::open_channel::cereal::CerealField::pour_in_field(package)?;)*
+                ::std::result::Result::Ok(())
+            }
+        }
+    })
+}
+
+/// Checks that `Option` fields only come last, and only in an extensible
+/// box.
+fn check_optional(types: &[&syn::Type], extensible: bool) -> syn::Result<()> {
+    let mut optional = false;
+    for ty in types {
+        if is_option(ty) {
+            if !extensible {
+                return Err(syn::Error::new_spanned(
+                    ty,
+                    "Option fields need an extensible box, as in #[cereal(id = ..., extensible)]",
+                ));
+            }
+            optional = true;
+        } else if optional {
+            return Err(syn::Error::new_spanned(
+                ty,
+                "only the last fields of a CerealBox can be Option fields",
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn is_option(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+/// The settings given in the `#[cereal(...)]` attributes of a box.
+struct BoxAttrs {
+    id: u8,
+    extensible: bool,
+}
+
+/// Reads the id and flags out of the `#[cereal(id = ...)]` attribute.
+fn box_attrs(input: &DeriveInput) -> syn::Result<BoxAttrs> {
+    let mut id = None;
+    let mut extensible = false;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("cereal")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("id") {
+                let lit: LitInt = meta.value()?.parse()?;
+                id = Some(lit.base10_parse::<u8>()?);
+                Ok(())
+            } else if meta.path.is_ident("extensible") {
+                extensible = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported cereal attribute"))
+            }
+        })?;
+    }
+    let id = id.ok_or_else(|| {
+        syn::Error::new_spanned(
+            &input.ident,
+            "missing #[cereal(id = ...)] attribute on CerealBox",
+        )
+    })?;
+    Ok(BoxAttrs { id, extensible })
+}
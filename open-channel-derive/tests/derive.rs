@@ -0,0 +1,172 @@
+use open_channel::cereal::{CerealBox, CerealStream, Packager};
+use open_channel::error::CerealError;
+use open_channel::serial_params::{CharLength, Parity, StopBits};
+
+#[derive(CerealBox, PartialEq, Debug, Default)]
+#[cereal(id = 1)]
+struct Ping;
+
+#[derive(CerealBox, PartialEq, Debug, Default)]
+#[cereal(id = 6)]
+struct AdcData {
+    channel: u8,
+    data: Vec<i16>,
+}
+
+#[derive(CerealBox, PartialEq, Debug, Default)]
+#[cereal(id = 8)]
+struct SerialParams {
+    channel: u8,
+    baud: u32,
+    char_len: CharLength,
+    parity: Parity,
+    stop: StopBits,
+}
+
+#[derive(CerealBox, PartialEq, Debug, Default)]
+#[cereal(id = 9)]
+struct SerialData(u8, String);
+
+#[test]
+fn unit_box() {
+    let mut stream = CerealStream::new();
+    Ping.pour_out(&mut stream).unwrap();
+    assert!(stream.is_empty());
+    assert_eq!(Ping.get_id(), 1);
+}
+
+#[test]
+fn vec_length_prefix() {
+    let adc = AdcData {
+        channel: 1,
+        data: [1024, 1999, 0, -800, -900].to_vec(),
+    };
+
+    let mut packager = Packager::new();
+    packager.unpack(&adc).unwrap();
+
+    let mut wire: Vec<u8> = Vec::new();
+    packager.write_to(&mut wire).unwrap();
+    assert_eq!(
+        wire,
+        [6, 1, 10, 0, 0, 4, 207, 7, 0, 0, 224, 252, 124, 252]
+    );
+
+    let mut stream = CerealStream::new();
+    stream.push_bytes(&wire[1..]).unwrap();
+    let mut copy = AdcData::default();
+    copy.pour_in(&mut stream).unwrap();
+    assert_eq!(copy, adc);
+}
+
+#[test]
+fn serial_enums() {
+    let params = SerialParams {
+        channel: 1,
+        baud: 19200,
+        char_len: CharLength::Seven,
+        parity: Parity::Even,
+        stop: StopBits::Two,
+    };
+
+    let mut stream = CerealStream::new();
+    params.pour_out(&mut stream).unwrap();
+    assert_eq!(stream.get_vec(), &[1, 0, 75, 0, 0, 7, 1, 2]);
+
+    let mut copy = SerialParams::default();
+    copy.pour_in(&mut stream).unwrap();
+    assert_eq!(copy, params);
+
+    stream.push_bytes(&[1, 0, 75, 0, 0, 9, 1, 2]).unwrap();
+    assert_eq!(
+        copy.pour_in(&mut stream),
+        Err(CerealError::InvalidEnumValue { name: "CharLength", value: 9 })
+    );
+}
+
+#[test]
+fn tuple_box() {
+    let data = SerialData(2, String::from("AT"));
+
+    let mut stream = CerealStream::new();
+    data.pour_out(&mut stream).unwrap();
+    assert_eq!(stream.get_vec(), &[2, 2, 0, b'A', b'T']);
+
+    let mut copy = SerialData::default();
+    copy.pour_in(&mut stream).unwrap();
+    assert_eq!(copy, data);
+}
+
+#[test]
+fn encoded_lengths() {
+    assert_eq!(Ping.fixed_len(), Some(0));
+    assert_eq!(SerialParams::default().fixed_len(), Some(8));
+
+    let adc = AdcData {
+        channel: 1,
+        data: [1024, 1999].to_vec(),
+    };
+    assert_eq!(adc.fixed_len(), None);
+    assert_eq!(adc.encoded_len(), Ok(7));
+}
+
+#[derive(CerealBox, PartialEq, Debug, Default)]
+#[cereal(id = 7, extensible)]
+struct Status {
+    rcv_count: u16,
+    snd_count: u16,
+    rcv_fails: Option<u16>,
+}
+
+#[test]
+fn extensible_box() {
+    let status = Status {
+        rcv_count: 260,
+        snd_count: 270,
+        rcv_fails: Some(3),
+    };
+    assert!(status.extensible());
+    assert_eq!(status.fixed_len(), None);
+
+    let mut packager = Packager::new();
+    packager.add_flavor::<Status>();
+    packager.unpack(&status).unwrap();
+    packager.push_bytes(&[7, 4, 4, 1, 14, 1]).unwrap();
+
+    let mut wire: Vec<u8> = Vec::new();
+    packager.write_to(&mut wire).unwrap();
+    assert_eq!(wire, [7, 6, 4, 1, 14, 1, 3, 0, 7, 4, 4, 1, 14, 1]);
+
+    packager.push_bytes(&wire).unwrap();
+    let msg = packager.pack().unwrap();
+    assert_eq!(msg.downcast_ref::<Status>(), Some(&status));
+    let msg = packager.pack().unwrap();
+    assert_eq!(msg.downcast_ref::<Status>().unwrap().rcv_fails, None);
+
+    packager.push_bytes(&[7, 1, 4]).unwrap();
+    assert_eq!(
+        packager.pack().unwrap_err().root(),
+        &CerealError::FrameTooShort { len: 1 }
+    );
+}
+
+#[test]
+fn extensible_box_longer_than_the_stream() {
+    let mut packager = Packager::with_limit(8);
+    packager.add_flavor::<Status>();
+    packager.push_bytes(&[7, 0xff, 0x7f, 1, 2]).unwrap();
+
+    // 16383 bytes would never fit, so the box is dropped rather than
+    // waited for
+    assert_eq!(
+        packager.pack().unwrap_err().root(),
+        &CerealError::LengthOverflow { len: 16383, max: 8 }
+    );
+    assert_eq!(packager.pack().unwrap_err().root(), &CerealError::UnknownId { id: 1 });
+    assert_eq!(packager.pack().unwrap_err().root(), &CerealError::UnknownId { id: 2 });
+    assert!(packager.is_empty());
+
+    let status = Status { rcv_count: 1, snd_count: 2, rcv_fails: None };
+    packager.unpack(&status).unwrap();
+    assert_eq!(packager.pack().unwrap().downcast_ref::<Status>(), Some(&status));
+}
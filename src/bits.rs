@@ -0,0 +1,233 @@
+use super::cereal::{CerealRead, CerealStream};
+use super::error::CerealError;
+
+/// The order in which bits fill each byte of a bit field.
+///
+/// # Examples
+///
+/// ```
+///   use open_channel::bits::BitOrder;
+///   use open_channel::cereal::CerealStream;
+///
+///   let mut stream = CerealStream::new();
+///   stream.bit_writer(BitOrder::LsbFirst).write_bits(0b110, 3).unwrap();
+///   stream.bit_writer(BitOrder::MsbFirst).write_bits(0b110, 3).unwrap();
+///   assert_eq!(stream.get_vec(), &[0b0000_0110, 0b1100_0000]);
+///
+///   assert_eq!(stream.bit_reader(BitOrder::LsbFirst).read_bits(3), Ok(0b110));
+///   assert_eq!(stream.bit_reader(BitOrder::MsbFirst).read_bits(3), Ok(0b110));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum BitOrder {
+    /// Fields start at the most significant bit of a byte and each value
+    /// is written most significant bit first.
+    #[default]
+    MsbFirst,
+    /// Fields start at the least significant bit of a byte and each value
+    /// is written least significant bit first.
+    LsbFirst,
+}
+
+/// Packs values of arbitrary bit width into a [`CerealStream`].
+///
+/// A partially filled byte is padded with zeros and pushed when the
+/// writer is aligned, finished or dropped. Bytes are pushed as they fill,
+/// so a write into a bounded stream reports when the stream is full.
+///
+/// # Examples
+///
+/// ```
+///   use open_channel::bits::{BitOrder, BitReader};
+///   use open_channel::cereal::CerealStream;
+///
+///   let mut stream = CerealStream::new();
+///   let mut bits = stream.bit_writer(BitOrder::MsbFirst);
+///   bits.write_bits(0b1, 1).unwrap();
+///   bits.write_bits(0b01, 2).unwrap();
+///   bits.write_bits(0b10, 2).unwrap();
+///   bits.align().unwrap();
+///   bits.write_bits(0x3ff, 10).unwrap();
+///   bits.finish().unwrap();
+///   assert_eq!(stream.get_vec(), &[0b1011_0000, 0xff, 0b1100_0000]);
+///
+///   let mut bits = BitReader::new(&mut stream, BitOrder::MsbFirst);
+///   assert_eq!(bits.read_bits(1), Ok(0b1));
+///   assert_eq!(bits.read_bits(2), Ok(0b01));
+///   assert_eq!(bits.read_bits(2), Ok(0b10));
+///   bits.align();
+///   assert_eq!(bits.read_bits(10), Ok(0x3ff));
+/// ```
+pub struct BitWriter<'a> {
+    stream: &'a mut CerealStream,
+    order: BitOrder,
+    current: u8,
+    filled: u8,
+}
+
+impl<'a> BitWriter<'a> {
+    /// Creates a new [`BitWriter`] that pushes into `stream`.
+    pub fn new(stream: &'a mut CerealStream, order: BitOrder) -> Self {
+        Self {
+            stream,
+            order,
+            current: 0,
+            filled: 0,
+        }
+    }
+
+    /// Writes the low `width` bits of `value`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Overflow`] if a filled byte does not fit in
+    /// the stream.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is not between 1 and 32.
+    pub fn write_bits(&mut self, value: u32, width: u8) -> Result<(), CerealError> {
+        assert!((1..=32).contains(&width), "bit width {} out of range", width);
+        for i in 0..width {
+            let shift = match self.order {
+                BitOrder::MsbFirst => width - 1 - i,
+                BitOrder::LsbFirst => i,
+            };
+            self.write_bit((value >> shift) & 1 == 1)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a single bit.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Overflow`] if a filled byte does not fit in
+    /// the stream.
+    pub fn write_bool(&mut self, bit: bool) -> Result<(), CerealError> {
+        self.write_bit(bit)
+    }
+
+    /// Pads the current byte with zeros and pushes it, so the next field
+    /// starts on a byte boundary. Does nothing if already aligned.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Overflow`] if the byte does not fit in the
+    /// stream. The partial byte is kept in that case.
+    pub fn align(&mut self) -> Result<(), CerealError> {
+        if self.filled > 0 {
+            self.stream.push_u8(self.current)?;
+            self.current = 0;
+            self.filled = 0;
+        }
+        Ok(())
+    }
+
+    /// Aligns the writer and releases the stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Overflow`] if the last byte does not fit in
+    /// the stream.
+    pub fn finish(mut self) -> Result<(), CerealError> {
+        self.align()
+    }
+
+    fn write_bit(&mut self, bit: bool) -> Result<(), CerealError> {
+        if self.filled == 8 {
+            self.align()?;
+        }
+        if bit {
+            self.current |= match self.order {
+                BitOrder::MsbFirst => 0x80 >> self.filled,
+                BitOrder::LsbFirst => 1 << self.filled,
+            };
+        }
+        self.filled += 1;
+        if self.filled == 8 {
+            self.align()?;
+        }
+        Ok(())
+    }
+}
+
+/// Pushes any partially filled byte, ignoring a full stream. Use
+/// [`BitWriter::finish`] to find out whether the last byte made it.
+impl Drop for BitWriter<'_> {
+    fn drop(&mut self) {
+        let _ = self.align();
+    }
+}
+
+/// Unpacks values of arbitrary bit width from any [`CerealRead`] source.
+///
+/// Bytes are pulled from the source one at a time as they are needed.
+pub struct BitReader<'a, R: CerealRead + ?Sized> {
+    source: &'a mut R,
+    order: BitOrder,
+    current: u8,
+    left: u8,
+}
+
+impl<'a, R: CerealRead + ?Sized> BitReader<'a, R> {
+    /// Creates a new [`BitReader`] that pops from `source`.
+    pub fn new(source: &'a mut R, order: BitOrder) -> Self {
+        Self {
+            source,
+            order,
+            current: 0,
+            left: 0,
+        }
+    }
+
+    /// Reads a `width` bit value.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Underflow`] if the source runs out of bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is not between 1 and 32.
+    pub fn read_bits(&mut self, width: u8) -> Result<u32, CerealError> {
+        assert!((1..=32).contains(&width), "bit width {} out of range", width);
+        let mut value = 0;
+        for i in 0..width {
+            let bit = self.read_bit()? as u32;
+            value |= match self.order {
+                BitOrder::MsbFirst => bit << (width - 1 - i),
+                BitOrder::LsbFirst => bit << i,
+            };
+        }
+        Ok(value)
+    }
+
+    /// Reads a single bit.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Underflow`] if the source runs out of bytes.
+    pub fn read_bool(&mut self) -> Result<bool, CerealError> {
+        self.read_bit()
+    }
+
+    /// Discards the rest of the current byte, so the next field is read
+    /// from a byte boundary. Does nothing if already aligned.
+    pub fn align(&mut self) {
+        self.left = 0;
+    }
+
+    fn read_bit(&mut self) -> Result<bool, CerealError> {
+        if self.left == 0 {
+            self.current = self.source.try_pop_byte()?;
+            self.left = 8;
+        }
+        let used = 8 - self.left;
+        self.left -= 1;
+        let mask = match self.order {
+            BitOrder::MsbFirst => 0x80 >> used,
+            BitOrder::LsbFirst => 1 << used,
+        };
+        Ok(self.current & mask != 0)
+    }
+}
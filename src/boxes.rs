@@ -1,7 +1,14 @@
-use open_channel::cereal::{CerealBox, CerealStream};
-use open_channel::serial_params::{CharLength, Parity, StopBits};
+//! The standard set of boxes spoken by the firmware, and the [`Message`]
+//! enum over them.
 
-#[derive(PartialEq, Debug, Default)]
+use serde_derive::{Deserialize, Serialize};
+
+use super::cereal::{CerealBox, CerealField, CerealRead, CerealReader, CerealStream, FirmwareVersion, LengthPrefix, Packager};
+use super::error::CerealError;
+use super::serial_params::{CharLength, LineSettings, Parity, StopBits};
+
+/// Asks the peer to answer with a [`Pong`].
+#[derive(PartialEq, Debug, Default, Serialize, Deserialize)]
 pub struct Ping {}
 
 impl CerealBox for Ping{
@@ -9,43 +16,55 @@ impl CerealBox for Ping{
         1
     }
 
-    fn pour_in(&mut self, _: &mut CerealStream) -> Result<(), String> {
-        self.consume();
+    fn fixed_len(&self) -> Option<usize> {
+        Some(0)
+    }
+
+    fn pour_in(&mut self, _: &mut dyn CerealRead) -> Result<(), CerealError> {
         Ok(())
     }
 
 }
 
-#[derive(PartialEq, Debug, Default)]
+/// The answer to a [`Ping`].
+#[derive(PartialEq, Debug, Default, Serialize, Deserialize)]
 pub struct Pong {}
 
 impl CerealBox for Pong{
     fn get_id(&self) -> u8 {
         2
     }
-    fn pour_in(&mut self, _: &mut CerealStream) -> Result<(), String> {
-        self.consume();
+
+    fn fixed_len(&self) -> Option<usize> {
+        Some(0)
+    }
+    fn pour_in(&mut self, _: &mut dyn CerealRead) -> Result<(), CerealError> {
         Ok(())
     }
 
 }
 
-#[derive(PartialEq, Debug, Default)]
+/// Asks the peer to answer with its [`VersionData`].
+#[derive(PartialEq, Debug, Default, Serialize, Deserialize)]
 pub struct VersionQuery {}
 
 impl CerealBox for VersionQuery{
     fn get_id(&self) -> u8 {
         3
     }
-    fn pour_in(&mut self, _: &mut CerealStream) -> Result<(), String> {
-        self.consume();
+
+    fn fixed_len(&self) -> Option<usize> {
+        Some(0)
+    }
+    fn pour_in(&mut self, _: &mut dyn CerealRead) -> Result<(), CerealError> {
         Ok(())
     }
 
 }
 
 
-#[derive(PartialEq, Debug, Default)]
+/// The firmware version of the peer.
+#[derive(PartialEq, Debug, Default, Serialize, Deserialize)]
 pub struct VersionData {
     pub major: u8,
     pub minor: u8,
@@ -58,22 +77,30 @@ impl CerealBox for VersionData{
         4
     }
 
-    fn pour_out(&self, package: &mut CerealStream) {
-        package.push_bytes(&[self.major, self.minor, self.maintenance, self.build]);
+    fn fixed_len(&self) -> Option<usize> {
+        Some(4)
     }
 
-    fn pour_in(&mut self, package: &mut CerealStream) -> Result<(), String> {
-        self.major = package.pop_byte();
-        self.minor = package.pop_byte();
-        self.maintenance = package.pop_byte();
-        self.build = package.pop_byte();
+    fn pour_out(&self, package: &mut CerealStream) -> Result<(), CerealError> {
+        package.push_bytes(&[self.major, self.minor, self.maintenance, self.build])?;
+        Ok(())
+    }
 
-        self.consume();
+    fn pour_in(&mut self, package: &mut dyn CerealRead) -> Result<(), CerealError> {
+        self.major = package.try_pop_byte()?;
+        self.minor = package.try_pop_byte()?;
+        self.maintenance = package.try_pop_byte()?;
+        self.build = package.try_pop_byte()?;
         Ok(())
     }
+
+    fn peer_version(&self) -> Option<FirmwareVersion> {
+        Some(FirmwareVersion::new(self.major, self.minor, self.maintenance, self.build))
+    }
 }
 
-#[derive(PartialEq, Debug, Default)]
+/// Asks the peer to sample an ADC channel.
+#[derive(PartialEq, Debug, Default, Serialize, Deserialize)]
 pub struct AdcQuery {
     pub channel: u8,
     pub length: u8,
@@ -85,22 +112,26 @@ impl CerealBox for AdcQuery{
         5
     }
 
-    fn pour_out(&self, package: &mut CerealStream) {
-        package.push_bytes(&[self.channel, self.length].to_vec());
-        package.push_bytes(&self.increment_usec.to_le_bytes());
+    fn fixed_len(&self) -> Option<usize> {
+        Some(6)
     }
 
-    fn pour_in(&mut self, package: &mut CerealStream) -> Result<(), String> {
-        self.channel = package.pop_byte();
-        self.length = package.pop_byte();
-        self.increment_usec = u32::from_le_bytes(package.pop_bytes(4).try_into().unwrap());
+    fn pour_out(&self, package: &mut CerealStream) -> Result<(), CerealError> {
+        package.push_bytes(&[self.channel, self.length])?;
+        package.push_u32(self.increment_usec)?;
+        Ok(())
+    }
 
-        self.consume();
+    fn pour_in(&mut self, package: &mut dyn CerealRead) -> Result<(), CerealError> {
+        self.channel = package.try_pop_byte()?;
+        self.length = package.try_pop_byte()?;
+        self.increment_usec = package.try_pop_u32()?;
         Ok(())
     }
 }
 
-#[derive(PartialEq, Debug, Default)]
+/// Samples read from an ADC channel.
+#[derive(PartialEq, Debug, Default, Serialize, Deserialize)]
 pub struct AdcData {
     pub channel: u8,
     pub data: Vec<i16>
@@ -111,30 +142,38 @@ impl CerealBox for AdcData{
         6
     }
 
-    fn pour_out(&self, package: &mut CerealStream) {
-        package.push_bytes(&[self.channel]);
-        let data: Vec<u8> = self.data.iter()
-            .flat_map(|&value| value.to_le_bytes().to_vec())
-            .collect();
-        let length: u16 = data.len() as u16;
-        package.push_bytes(&length.to_le_bytes().to_vec());
-        package.push_bytes(&data);
-    }
-
-    fn pour_in(&mut self, package: &mut CerealStream) -> Result<(), String> {
-        self.channel = package.pop_byte();
-        let length = u16::from_le_bytes(package.pop_bytes(2).try_into().unwrap());
-        self.data = package
-            .pop_bytes(length as usize)
-            .chunks(2)
-            .map(|chunk| {
-                let mut bytes = [0; 2];
-                bytes.copy_from_slice(chunk);
-                i16::from_le_bytes(bytes)
-            })
-            .collect();
+    fn encoded_len(&self) -> Result<usize, CerealError> {
+        Ok(3 + self.data.len() * 2)
+    }
+
+    fn pour_out(&self, package: &mut CerealStream) -> Result<(), CerealError> {
+        let len = self.data.len() * 2;
+        let length = u16::try_from(len).map_err(|_| CerealError::LengthOverflow {
+            len,
+            max: u16::MAX as usize,
+        })?;
+        package.push_u8(self.channel)?;
+        package.push_u16(length)?;
+        for &value in &self.data {
+            package.push_i16(value)?;
+        }
+        Ok(())
+    }
 
-        self.consume();
+    fn pour_in(&mut self, package: &mut dyn CerealRead) -> Result<(), CerealError> {
+        self.channel = package.try_pop_byte()?;
+        // the samples are taken as a whole, so a bad length drops them all
+        let bytes = package.try_pop_blob(LengthPrefix::U16, u16::MAX as usize)?;
+        if bytes.len() % 2 != 0 {
+            return Err(CerealError::InvalidFormat(format!(
+                "AdcData length {} is not a whole number of samples",
+                bytes.len()
+            )));
+        }
+        let mut samples = CerealReader::with_endian(&bytes, package.endian());
+        self.data = (0..bytes.len() / 2)
+            .map(|_| samples.try_pop_i16())
+            .collect::<Result<_, _>>()?;
         Ok(())
     }
 }
@@ -144,7 +183,8 @@ impl CerealBox for AdcData{
 /// # Examples
 /// ```
 ///
-///   use open_channel::boxes::SerialParams
+///   use open_channel::boxes::SerialParams;
+///   use open_channel::serial_params::{CharLength, Parity, StopBits};
 ///
 ///   let pstr = "9600:8E1";
 ///   let sp = SerialParams::from_str(1, pstr);
@@ -159,7 +199,7 @@ impl CerealBox for AdcData{
 ///
 /// ```
 ///
-#[derive(PartialEq, Debug, Default)]
+#[derive(PartialEq, Debug, Default, Serialize, Deserialize)]
 pub struct SerialParams {
     pub channel: u8,
     pub baud: u32,
@@ -173,50 +213,85 @@ impl CerealBox for SerialParams {
         8
     }
 
-    fn pour_out(&self, package: &mut CerealStream) {
-        package.push_bytes(&[self.channel]);
-        package.push_bytes(&self.baud.to_le_bytes());
-        package.push_bytes(&[self.char_len.get_byte(), self.parity.get_byte(), self.stop.get_byte()]);
+    fn fixed_len(&self) -> Option<usize> {
+        Some(8)
+    }
 
+    fn pour_out(&self, package: &mut CerealStream) -> Result<(), CerealError> {
+        self.pour_out_layout(self.layout_version(), package)
     }
 
-    fn pour_in(&mut self, package: &mut CerealStream) -> Result<(), String> {
+    fn pour_in(&mut self, package: &mut dyn CerealRead) -> Result<(), CerealError> {
+        self.pour_in_layout(self.layout_version(), package)
+    }
 
-        self.channel = package.pop_byte();
-        self.baud = u32::from_le_bytes(package.pop_bytes(4).try_into().unwrap());
-        self.char_len = CharLength::from_byte(&package.pop_byte()).unwrap_or(CharLength::Eight);
-        self.parity = Parity::from_byte(&package.pop_byte()).unwrap_or(Parity::None);
-        self.stop = StopBits::from_byte(&package.pop_byte()).unwrap_or(StopBits::One);
+    /// Layout 0 predates the char length byte, which was added in
+    /// firmware 1.1.
+    fn layout_version(&self) -> u8 {
+        1
+    }
 
-        self.consume();
-        Ok(())
+    fn layout_for_peer(&self, peer: &FirmwareVersion) -> u8 {
+        if *peer < CHAR_LEN_FIRMWARE { 0 } else { 1 }
+    }
+
+    fn pour_out_layout(&self, layout: u8, package: &mut CerealStream) -> Result<(), CerealError> {
+        if layout > 1 {
+            return Err(CerealError::UnsupportedLayout { layout });
+        }
+        package.push_u8(self.channel)?;
+        package.push_u32(self.baud)?;
+        if layout >= 1 {
+            self.char_len.pour_out_field(package)?;
+        }
+        self.parity.pour_out_field(package)?;
+        self.stop.pour_out_field(package)
+    }
 
+    fn pour_in_layout(&mut self, layout: u8, package: &mut dyn CerealRead) -> Result<(), CerealError> {
+        if layout > 1 {
+            return Err(CerealError::UnsupportedLayout { layout });
+        }
+        self.channel = package.try_pop_byte()?;
+        self.baud = package.try_pop_u32()?;
+        self.char_len = match layout {
+            0 => CharLength::Eight,
+            _ => CharLength::pour_in_field(package)?,
+        };
+        self.parity = Parity::pour_in_field(package)?;
+        self.stop = StopBits::pour_in_field(package)?;
+        Ok(())
     }
 }
 
+/// The first firmware that sends the char length of [`SerialParams`].
+const CHAR_LEN_FIRMWARE: FirmwareVersion = FirmwareVersion::new(1, 1, 0, 0);
+
 impl SerialParams{
 
     /// create a Serial Parameters from setting string.
-    pub fn from_str(channel: u8, settings: &str) -> Result<Self, &str> {
+    pub fn from_str(channel: u8, settings: &str) -> Result<Self, CerealError> {
         let parts: Vec<&str> = settings.split(':').collect();
         if (parts.len() != 2) | !([3, 5].contains(&parts[1].len())) {
-            return Err("invalid Params format")
+            return Err(CerealError::InvalidFormat(String::from("invalid Params format")))
         }
 
-        let mut params = SerialParams::default();
-        params.channel = channel;
+        let mut params = SerialParams {
+            channel,
+            ..Default::default()
+        };
 
         let b = parts[0].parse();
         match b {
             Ok(b) => {params.baud = b},
-            Err(_) => { return Err("invalid baud")}
+            Err(_) => { return Err(CerealError::InvalidFormat(String::from("invalid baud")))}
         }
 
         let l = parts[1][0..1].to_string();
         match l.as_str() {
             "8" => {params.char_len = CharLength::Eight},
             "7" => {params.char_len = CharLength::Seven},
-            _ => {return Err("invalid char length")}
+            _ => {return Err(CerealError::InvalidFormat(String::from("invalid char length")))}
         }
 
         let p = parts[1][1..2].to_uppercase();
@@ -224,7 +299,7 @@ impl SerialParams{
             "E" => {params.parity = Parity::Even},
             "O" => {params.parity = Parity::Odd},
             "N" => {params.parity = Parity::None},
-            _ => {return Err("invalid parity")}
+            _ => {return Err(CerealError::InvalidFormat(String::from("invalid parity")))}
         }
 
         let s = parts[1][2..].to_uppercase();
@@ -232,50 +307,977 @@ impl SerialParams{
             "1" => {params.stop = StopBits::One},
             "2" => {params.stop = StopBits::Two},
             "1.5" => {params.stop = StopBits::OneAndHalf},
-            _ => {return Err("invalid parity")}
+            _ => {return Err(CerealError::InvalidFormat(String::from("invalid stop bits")))}
         }
 
         Ok(params)
 
     }
+
+    /// Returns the char length, parity and stop bits packed into a
+    /// single byte.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///   use open_channel::boxes::SerialParams;
+    ///   use open_channel::serial_params::LineSettings;
+    ///
+    ///   let sp = SerialParams::from_str(1, "9600:7O2").unwrap();
+    ///   let byte = sp.line_settings().get_byte();
+    ///   assert_eq!(byte, 0b1101_0000);
+    ///
+    ///   let mut copy = SerialParams{ channel: 1, baud: 9600, ..Default::default() };
+    ///   copy.set_line_settings(LineSettings::from_byte(&byte).unwrap());
+    ///   assert_eq!(copy, sp);
+    /// ```
+    pub fn line_settings(&self) -> LineSettings {
+        LineSettings {
+            char_len: self.char_len,
+            parity: self.parity,
+            stop: self.stop,
+        }
+    }
+
+    /// Sets the char length, parity and stop bits from their packed
+    /// form.
+    pub fn set_line_settings(&mut self, settings: LineSettings) {
+        self.char_len = settings.char_len;
+        self.parity = settings.parity;
+        self.stop = settings.stop;
+    }
+}
+
+
+/// Frame counters of the peer.
+#[derive(PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Status {
+    pub rcv_count: u16,
+    pub snd_count: u16,
+    pub rcv_fails: u16,
+}
+
+impl CerealBox for Status{
+    fn get_id(&self) -> u8 {
+        7
+    }
+
+    fn fixed_len(&self) -> Option<usize> {
+        Some(6)
+    }
+
+    fn pour_out(&self, package: &mut CerealStream) -> Result<(), CerealError> {
+        package.push_u16(self.rcv_count)?;
+        package.push_u16(self.snd_count)?;
+        package.push_u16(self.rcv_fails)?;
+        Ok(())
+    }
+
+    fn pour_in(&mut self, package: &mut dyn CerealRead) -> Result<(), CerealError> {
+        self.rcv_count = package.try_pop_u16()?;
+        self.snd_count = package.try_pop_u16()?;
+        self.rcv_fails = package.try_pop_u16()?;
+        Ok(())
+    }
 }
 
+/// Characters sent or received on a Serial Port.
+#[derive(PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct SerialData {
+    pub channel: u8,
+    pub data: String,
+}
+
+impl CerealBox for SerialData{
+    fn get_id(&self) -> u8 {
+        9
+    }
+
+    fn pour_out(&self, package: &mut CerealStream) -> Result<(), CerealError> {
+        package.push_u8(self.channel)?;
+        package.push_str(&self.data, LengthPrefix::U16)?;
+        Ok(())
+    }
+
+    fn pour_in(&mut self, package: &mut dyn CerealRead) -> Result<(), CerealError> {
+        self.channel = package.try_pop_byte()?;
+        self.data = package.try_pop_string(LengthPrefix::U16, u16::MAX as usize)?;
+        Ok(())
+    }
+}
+
+/// Builds the [`Message`] enum over the box types, so that the enum and
+/// the boxes share one set of `pour_in`/`pour_out` implementations and
+/// always produce the same bytes.
+macro_rules! protocol {
+    ($($name:ident),* $(,)?) => {
+        /// Every message of the protocol, for code that wants to `match`
+        /// on them. Each variant holds the box that puts it on the wire.
+        #[derive(PartialEq, Debug)]
+        pub enum Message {
+            $($name($name),)*
+        }
+
+        impl Message {
+            /// Returns the box that holds the message.
+            pub fn as_box(&self) -> &dyn CerealBox {
+                match self {
+                    $(Self::$name(msg) => msg,)*
+                }
+            }
+
+            /// Returns the type id of the message.
+            pub fn get_typeid(&self) -> u8 {
+                self.as_box().get_id()
+            }
+
+            /// Turns a box packed by a [`Packager`] into a message.
+            ///
+            /// # Errors
+            ///
+            /// Returns the box unchanged if it is not part of the protocol.
+            pub fn from_box(msg: Box<dyn CerealBox>) -> Result<Self, Box<dyn CerealBox>> {
+                $(
+                    let msg = match msg.downcast::<$name>() {
+                        Ok(msg) => return Ok(Self::$name(*msg)),
+                        Err(msg) => msg,
+                    };
+                )*
+                Err(msg)
+            }
+
+            /// Adds every box of the protocol to a packager.
+            pub fn register(packager: &mut Packager) {
+                $(packager.add_flavor::<$name>();)*
+            }
+
+            /// Pours the type id and then the message into a stream in its
+            /// current layout, the same as [`Packager::unpack`] lays out its
+            /// box before it knows the version of its peer.
+            ///
+            /// # Errors
+            ///
+            /// This function will return an error if the stream has no
+            /// room for the message.
+            pub fn serialize(&self, out: &mut CerealStream) -> Result<(), CerealError> {
+                self.serialize_for(None, out)
+            }
+
+            /// Pours the type id and then the message into a stream in the
+            /// layout a peer running `peer` firmware uses, the same as a
+            /// [`Packager`] with that peer version lays out its box.
+            ///
+            /// # Errors
+            ///
+            /// This function will return an error if the stream has no
+            /// room for the message.
+            pub fn serialize_for(&self, peer: Option<&FirmwareVersion>, out: &mut CerealStream) -> Result<(), CerealError> {
+                let msg = self.as_box();
+                let layout = peer.map_or(msg.layout_version(), |peer| msg.layout_for_peer(peer));
+                out.push_u8(self.get_typeid())?;
+                msg.pour_out_layout(layout, out)
+            }
+
+            /// Pours a type id and then the message it names out of a
+            /// source, in the current layout. Nothing is consumed if that
+            /// fails.
+            ///
+            /// # Errors
+            ///
+            /// Returns [`CerealError::UnknownId`] if the id is not part of
+            /// the protocol, or any error raised by the box.
+            pub fn deserialize(source: &mut dyn CerealRead) -> Result<Self, CerealError> {
+                Self::deserialize_for(None, source)
+            }
+
+            /// Pours a type id and then the message it names out of a
+            /// source, in the layout a peer running `peer` firmware uses.
+            /// Nothing is consumed if that fails.
+            ///
+            /// # Errors
+            ///
+            /// Returns [`CerealError::UnknownId`] if the id is not part of
+            /// the protocol, or any error raised by the box.
+            pub fn deserialize_for(peer: Option<&FirmwareVersion>, source: &mut dyn CerealRead) -> Result<Self, CerealError> {
+                source.mark();
+                let result = Self::deserialize_marked(peer, source);
+                match result {
+                    Ok(_) => source.commit(),
+                    Err(_) => source.rollback(),
+                }
+                result
+            }
+
+            fn deserialize_marked(peer: Option<&FirmwareVersion>, source: &mut dyn CerealRead) -> Result<Self, CerealError> {
+                let id = source.try_pop_byte()?;
+                $(
+                    if id == $name::default().get_id() {
+                        let mut msg = $name::default();
+                        let layout = peer.map_or(msg.layout_version(), |peer| msg.layout_for_peer(peer));
+                        msg.pour_in_layout(layout, source)?;
+                        return Ok(Self::$name(msg));
+                    }
+                )*
+                Err(CerealError::UnknownId { id })
+            }
+        }
+
+        $(
+            impl From<$name> for Message {
+                fn from(msg: $name) -> Self {
+                    Self::$name(msg)
+                }
+            }
+        )*
+    };
+}
+
+protocol! {
+    Ping,
+    Pong,
+    VersionQuery,
+    VersionData,
+    AdcQuery,
+    AdcData,
+    Status,
+    SerialParams,
+    SerialData,
+}
+
+impl Packager {
+    /// Creates a new [`Packager`] with every box of the standard
+    /// [`Message`] set added.
+    pub fn with_std_messages() -> Self {
+        let mut packager = Self::new();
+        Message::register(&mut packager);
+        packager
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn my_ser(){
+
+        let pstr = "9600:8E1";
+        let sp = SerialParams::from_str(1, pstr);
+
+        assert_eq!(sp, Ok(SerialParams{
+            channel: 1,
+            baud: 9600,
+            char_len: CharLength::Eight,
+            parity: Parity::Even,
+            stop: StopBits::One
+        }));
+
+        let pstr = "19200:7o2";
+        let sp = SerialParams::from_str(1, pstr);
+
+        assert_eq!(sp, Ok(SerialParams{
+            channel: 1,
+            baud: 19200,
+            char_len: CharLength::Seven,
+            parity: Parity::Odd,
+            stop: StopBits::Two
+        }));
+
+        let pstr = "4800:7n1.5";
+        let sp = SerialParams::from_str(1, pstr);
+
+        assert_eq!(sp, Ok(SerialParams{
+            channel: 1,
+            baud: 4800,
+            char_len: CharLength::Seven,
+            parity: Parity::None,
+            stop: StopBits::OneAndHalf
+        }));
+
+
+    }
+
+    #[test]
+    fn truncated_frame(){
+        let mut full = CerealStream::new();
+        AdcQuery{ channel: 1, length: 2, increment_usec: 3 }.pour_out(&mut full).unwrap();
+
+        let mut stream = CerealStream::new();
+        stream.push_bytes(&full.get_vec()[..4]).unwrap();
+
+        let mut query = AdcQuery::default();
+        assert_eq!(
+            query.pour_in(&mut stream),
+            Err(CerealError::Underflow { requested: 4, available: 2 })
+        );
+
+        let mut packager = Packager::new();
+        assert_eq!(
+            packager.pack().unwrap_err(),
+            CerealError::Underflow { requested: 1, available: 0 }
+        );
+    }
+
+    #[test]
+    fn error_context(){
+        let mut packager = Packager::new();
+        packager.add_flavor::<Ping>();
+        packager.add_flavor::<AdcData>();
+
+        packager.push_bytes(&[1, 9, 6, 1, 3, 0, 1, 2, 3]).unwrap();
+        assert_eq!(packager.pack().unwrap().get_id(), 1);
+
+        let err = packager.pack().unwrap_err();
+        assert_eq!(err, CerealError::UnknownId { id: 9 }.in_box(9, 1));
+
+        let err = packager.pack().unwrap_err();
+        assert_eq!(err.to_string(), "box 6 at offset 2: AdcData length 3 is not a whole number of samples");
+        assert!(matches!(err.root(), CerealError::InvalidFormat(_)));
+    }
+
+    #[test]
+    fn encoded_lengths(){
+        assert_eq!(VersionData::default().encoded_len(), Ok(4));
+        assert_eq!(AdcQuery::default().encoded_len(), Ok(6));
+        assert_eq!(AdcData{ channel: 1, data: vec![1, 2, 3] }.encoded_len(), Ok(9));
+
+        let mut packager = Packager::with_limit(6);
+        assert_eq!(
+            packager.unpack(&AdcQuery::default()),
+            Err(CerealError::Overflow { requested: 7, free: 6 })
+        );
+        assert!(packager.is_empty());
+    }
+
+    #[test]
+    fn fixed_frame_waits_for_all_bytes(){
+        let mut packager = Packager::new();
+        packager.add_flavor::<VersionData>();
+
+        packager.push_bytes(&[4, 1, 2, 3]).unwrap();
+        let err = packager.pack().unwrap_err();
+        assert_eq!(err.root(), &CerealError::Underflow { requested: 4, available: 3 });
+
+        packager.push_bytes(&[4]).unwrap();
+        assert_eq!(packager.pack().unwrap().get_id(), 4);
+    }
+
+    #[test]
+    fn retry_after_short_read(){
+        let mut full = CerealStream::new();
+        full.push_bytes(&[5]).unwrap();
+        AdcQuery{ channel: 1, length: 2, increment_usec: 3 }.pour_out(&mut full).unwrap();
+        let bytes = full.get_vec().to_vec();
+
+        let mut packager = Packager::new();
+        packager.add_flavor::<AdcQuery>();
+
+        packager.push_bytes(&bytes[..5]).unwrap();
+        assert!(packager.pack().is_err());
+        assert!(packager.pack().is_err());
+
+        packager.push_bytes(&bytes[5..]).unwrap();
+        assert_eq!(packager.pack().unwrap().get_id(), 5);
+        assert!(packager.is_empty());
+    }
+
+    #[test]
+    fn pour_in_from_slice(){
+        use crate::cereal::CerealReader;
+
+        let mut stream = CerealStream::new();
+        AdcData{ channel: 7, data: [1024, -900].to_vec() }.pour_out(&mut stream).unwrap();
+        let dma = stream.get_vec().to_vec();
+
+        let mut reader = CerealReader::new(&dma);
+        let mut adc = AdcData::default();
+        adc.pour_in(&mut reader).unwrap();
+
+        assert_eq!(adc, AdcData{ channel: 7, data: [1024, -900].to_vec() });
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn packager_over_io(){
+        let mut sender = Packager::new();
+        sender.unpack(&VersionData{ major: 1, minor: 2, maintenance: 3, build: 4 }).unwrap();
+        sender.unpack(&Ping::default()).unwrap();
+
+        let mut wire: Vec<u8> = Vec::new();
+        assert_eq!(sender.write_to(&mut wire).unwrap(), 6);
+        assert!(sender.is_empty());
+
+        let mut receiver = Packager::new();
+        receiver.add_flavor::<VersionData>();
+        receiver.add_flavor::<Ping>();
+        let mut link = wire.as_slice();
+        while receiver.read_from(&mut link).unwrap() > 0 {}
+
+        let version = receiver.pack().unwrap();
+        assert_eq!(
+            version.downcast_ref::<VersionData>(),
+            Some(&VersionData{ major: 1, minor: 2, maintenance: 3, build: 4 })
+        );
+        assert!(receiver.pack().unwrap().is::<Ping>());
+        assert!(receiver.is_empty());
+    }
+
+    #[test]
+    fn write_to_resumes_after_would_block(){
+        use std::io;
+
+        // takes a few bytes per call, then would block
+        struct Trickle {
+            wire: Vec<u8>,
+            budget: usize,
+        }
+
+        impl io::Write for Trickle {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.budget == 0 {
+                    return Err(io::ErrorKind::WouldBlock.into());
+                }
+                let n = buf.len().min(self.budget).min(2);
+                self.wire.extend_from_slice(&buf[..n]);
+                self.budget -= n;
+                Ok(n)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut sender = Packager::new();
+        sender.unpack(&VersionData{ major: 1, minor: 2, maintenance: 3, build: 4 }).unwrap();
+        sender.unpack(&Ping::default()).unwrap();
+
+        let mut link = Trickle { wire: Vec::new(), budget: 3 };
+        assert_eq!(sender.write_to(&mut link).unwrap(), 3);
+        assert!(!sender.is_empty());
+
+        link.budget = 10;
+        assert_eq!(sender.write_to(&mut link).unwrap(), 3);
+        assert!(sender.is_empty());
+        assert_eq!(link.wire, [4, 1, 2, 3, 4, 1]);
+    }
+
+    #[test]
+    fn packed_boxes_are_owned(){
+        let mut packager = Packager::new();
+        packager.add_flavor::<AdcData>();
+        packager.unpack(&AdcData{ channel: 1, data: vec![1, 2] }).unwrap();
+        packager.unpack(&AdcData{ channel: 2, data: vec![3] }).unwrap();
+
+        let first = packager.pack().unwrap();
+        let second = packager.pack().unwrap();
+
+        let data = std::thread::spawn(move || {
+            let first = first.downcast::<AdcData>().unwrap();
+            let second = second.downcast::<AdcData>().unwrap();
+            (first.data, second.data)
+        }).join().unwrap();
+        assert_eq!(data, (vec![1, 2], vec![3]));
+    }
+
+    #[test]
+    fn handlers_see_packed_boxes(){
+        use std::sync::mpsc;
+
+        let (tx, rx) = mpsc::channel();
+        let mut packager = Packager::new();
+        packager.on::<AdcData>(move |msg| tx.send(msg.data.clone()).unwrap());
+        packager.add_flavor::<Ping>();
+
+        packager.unpack(&AdcData{ channel: 1, data: vec![1, 2] }).unwrap();
+        packager.unpack(&Ping::default()).unwrap();
+        packager.unpack(&AdcData{ channel: 2, data: vec![3] }).unwrap();
+        while !packager.is_empty() {
+            packager.pack().unwrap();
+        }
+
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![vec![1, 2], vec![3]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Another flavor has id: 1")]
+    fn handler_for_another_flavor(){
+        let mut packager = Packager::new();
+        packager.add_factory(1, || Box::new(Pong::default()));
+        packager.on::<Ping>(|_| {});
+    }
+
+    #[test]
+    fn drain_messages(){
+        let mut packager = Packager::new();
+        packager.add_flavor::<Ping>();
+        packager.add_flavor::<AdcQuery>();
+
+        packager.unpack(&Ping::default()).unwrap();
+        packager.push_bytes(&[9]).unwrap();
+        packager.unpack(&AdcQuery{ channel: 1, length: 2, increment_usec: 3 }).unwrap();
+        packager.push_bytes(&[5, 1]).unwrap();
+
+        let msgs: Vec<_> = packager.drain_messages().collect();
+        assert_eq!(msgs.len(), 3);
+        assert!(msgs[0].as_ref().unwrap().is::<Ping>());
+        assert_eq!(msgs[1].as_ref().unwrap_err().root(), &CerealError::UnknownId { id: 9 });
+        assert_eq!(
+            msgs[2].as_ref().unwrap().downcast_ref::<AdcQuery>(),
+            Some(&AdcQuery{ channel: 1, length: 2, increment_usec: 3 })
+        );
+
+        assert!(!packager.is_empty());
+        assert_eq!(packager.drain_messages().count(), 0);
+    }
+
+    #[test]
+    fn bad_frame_is_dropped(){
+        let mut packager = Packager::new();
+        packager.add_flavor::<AdcData>();
+        packager.add_flavor::<Ping>();
+        packager.add_flavor::<SerialParams>();
+        packager.push_bytes(&[6, 7, 3, 0, 1, 2, 3]).unwrap();
+        packager.unpack(&Ping::default()).unwrap();
+        packager.push_bytes(&[8, 1, 0, 75, 0, 0, 8, 1, 9]).unwrap();
+        packager.unpack(&Ping::default()).unwrap();
+
+        // everything a failed box read goes with it, so none of its
+        // bytes come back as boxes of their own
+        let msgs: Vec<_> = packager.drain_messages().collect();
+        assert_eq!(msgs.len(), 4);
+        assert!(matches!(msgs[0].as_ref().unwrap_err().root(), CerealError::InvalidFormat(_)));
+        assert!(msgs[1].as_ref().unwrap().is::<Ping>());
+        assert_eq!(
+            msgs[2].as_ref().unwrap_err().root(),
+            &CerealError::InvalidEnumValue { name: "StopBits", value: 9 }
+        );
+        assert!(msgs[3].as_ref().unwrap().is::<Ping>());
+        assert!(packager.is_empty());
+    }
+
+    #[test]
+    fn layout_for_peer_version(){
+        let mut packager = Packager::new();
+        packager.add_flavor::<VersionData>();
+        packager.add_flavor::<SerialParams>();
+
+        packager.push_bytes(&[4, 1, 0, 9, 3]).unwrap();
+        packager.push_bytes(&[8, 1, 0, 75, 0, 0, 1, 1]).unwrap();
+
+        assert!(packager.pack().unwrap().is::<VersionData>());
+        assert_eq!(packager.peer_version(), Some(FirmwareVersion::new(1, 0, 9, 3)));
+
+        let params = packager.pack().unwrap();
+        assert_eq!(params.downcast_ref::<SerialParams>(), Some(&SerialParams{
+            channel: 1,
+            baud: 19200,
+            char_len: CharLength::Eight,
+            parity: Parity::Even,
+            stop: StopBits::One,
+        }));
+        assert!(packager.is_empty());
 
-#[test]
-fn my_ser(){
+        let mut sender = Packager::new();
+        sender.set_peer_version(Some(FirmwareVersion::new(1, 0, 9, 3)));
+        sender.unpack(params.as_ref()).unwrap();
+        sender.set_peer_version(Some(FirmwareVersion::new(1, 1, 0, 0)));
+        sender.unpack(params.as_ref()).unwrap();
 
-    let pstr = "9600:8E1";
-    let sp = SerialParams::from_str(1, pstr);
+        let mut wire: Vec<u8> = Vec::new();
+        sender.write_to(&mut wire).unwrap();
+        assert_eq!(wire, [8, 1, 0, 75, 0, 0, 1, 1, 8, 1, 0, 75, 0, 0, 8, 1, 1]);
+    }
+
+    #[test]
+    fn message_for_peer_version(){
+        let old = FirmwareVersion::new(1, 0, 9, 3);
+        let params = Message::from(SerialParams{
+            channel: 1,
+            baud: 9600,
+            char_len: CharLength::Eight,
+            parity: Parity::None,
+            stop: StopBits::One,
+        });
+
+        let mut sender = Packager::new();
+        sender.set_peer_version(Some(old));
+        sender.unpack(params.as_box()).unwrap();
+        let mut wire: Vec<u8> = Vec::new();
+        sender.write_to(&mut wire).unwrap();
+
+        let mut serial = CerealStream::new();
+        params.serialize_for(Some(&old), &mut serial).unwrap();
+        assert_eq!(serial.get_vec(), wire.as_slice());
+        assert_eq!(serial.get_vec(), &[8, 1, 128, 37, 0, 0, 0, 1]);
+        assert_eq!(Message::deserialize_for(Some(&old), &mut serial), Ok(params));
+        assert!(serial.is_empty());
+    }
+
+    #[test]
+    fn framing_recovers_from_lost_bytes(){
+        use crate::checksum::CRC32_STM32;
+        use crate::frame::Framing;
 
-    assert_eq!(sp, Ok(SerialParams{
-        channel: 1,
-        baud: 9600,
-        char_len: CharLength::Eight,
-        parity: Parity::Even,
-        stop: StopBits::One
-    }));
+        let mut sender = Packager::new();
+        sender.set_framing(Some(Framing::Sync { checksum: &CRC32_STM32, max_len: 64 }));
+        sender.unpack(&AdcQuery{ channel: 1, length: 2, increment_usec: 3 }).unwrap();
+        sender.unpack(&Status{ rcv_count: 260, snd_count: 270, rcv_fails: 1 }).unwrap();
+        sender.unpack(&Ping::default()).unwrap();
 
-    let pstr = "19200:7o2";
-    let sp = SerialParams::from_str(1, pstr);
+        let mut wire: Vec<u8> = Vec::new();
+        sender.write_to(&mut wire).unwrap();
+        assert_eq!(&wire[..6], &[0xA5, 0x5A, 7, 0, 5, 1]);
+        wire.remove(6);
 
-    assert_eq!(sp, Ok(SerialParams{
-        channel: 1,
-        baud: 19200,
-        char_len: CharLength::Seven,
-        parity: Parity::Odd,
-        stop: StopBits::Two
-    }));
+        let mut receiver = Packager::with_std_messages();
+        receiver.set_framing(Some(Framing::Sync { checksum: &CRC32_STM32, max_len: 64 }));
+        receiver.push_bytes(&wire).unwrap();
+        // a frame that passes its checksum but holds a broken box
+        let mut bad = CerealStream::new();
+        Framing::Sync { checksum: &CRC32_STM32, max_len: 64 }.encode(&[6, 1, 3, 0, 1, 2, 3], &mut bad).unwrap();
+        receiver.push_bytes(bad.get_vec()).unwrap();
+        receiver.unpack(&Pong::default()).unwrap();
+
+        let msgs: Vec<_> = receiver.drain_messages().collect();
+        assert_eq!(msgs.len(), 6);
+        assert!(matches!(msgs[0].as_ref().unwrap_err(), CerealError::ChecksumMismatch { .. }));
+        assert!(matches!(msgs[1].as_ref().unwrap_err(), CerealError::LostSync { .. }));
+        assert_eq!(
+            msgs[2].as_ref().unwrap().downcast_ref::<Status>(),
+            Some(&Status{ rcv_count: 260, snd_count: 270, rcv_fails: 1 })
+        );
+        assert!(msgs[3].as_ref().unwrap().is::<Ping>());
+        assert!(matches!(msgs[4].as_ref().unwrap_err().root(), CerealError::InvalidFormat(_)));
+        assert!(msgs[5].as_ref().unwrap().is::<Pong>());
+        assert!(receiver.is_empty());
+    }
+
+    #[test]
+    fn framing_rejects_corrupted_length(){
+        use crate::checksum::CRC16_CCITT;
+        use crate::frame::Framing;
+
+        let framing = Framing::Sync { checksum: &CRC16_CCITT, max_len: 32 };
+        let mut sender = Packager::new();
+        sender.set_framing(Some(framing));
+        let mut receiver = Packager::with_limit(64);
+        receiver.add_flavor::<Ping>();
+        receiver.set_framing(Some(framing));
+
+        receiver.push_bytes(&[0xA5, 0x5A, 0xFF, 0xFF, 1]).unwrap();
+        assert_eq!(
+            receiver.pack().unwrap_err(),
+            CerealError::LengthOverflow { len: 0xFFFF, max: 32 }
+        );
+
+        let mut pings = 0;
+        for _ in 0..10 {
+            sender.unpack(&Ping::default()).unwrap();
+            let mut wire: Vec<u8> = Vec::new();
+            sender.write_to(&mut wire).unwrap();
+            receiver.push_bytes(&wire).unwrap();
+            pings += receiver.drain_messages().filter(|msg| msg.is_ok()).count();
+        }
+        assert_eq!(pings, 10);
+        assert!(receiver.is_empty());
+
+        assert_eq!(
+            sender.unpack(&AdcData{ channel: 1, data: vec![0; 16] }),
+            Err(CerealError::LengthOverflow { len: 36, max: 32 })
+        );
+    }
 
-    let pstr = "4800:7n1.5";
-    let sp = SerialParams::from_str(1, pstr);
+    #[test]
+    fn cobs_framing_in_chunks(){
+        use crate::frame::Framing;
 
-    assert_eq!(sp, Ok(SerialParams{
-        channel: 1,
-        baud: 4800,
-        char_len: CharLength::Seven,
-        parity: Parity::None,
-        stop: StopBits::OneAndHalf
-    }));
+        let status = Status{ rcv_count: 260, snd_count: 270, rcv_fails: 1 };
+        let adc = AdcData{ channel: 7, data: vec![1024, 0, -800] };
 
+        let mut sender = Packager::new();
+        sender.set_framing(Some(Framing::Cobs));
+        sender.unpack(&status).unwrap();
+        sender.unpack(&adc).unwrap();
+        sender.unpack(&Ping::default()).unwrap();
 
+        let mut wire: Vec<u8> = Vec::new();
+        sender.write_to(&mut wire).unwrap();
+        assert_eq!(wire.iter().filter(|&&byte| byte == 0).count(), 3);
+        assert_eq!(&wire[..4], &[7, 7, 4, 1]);
+        // line noise inside the first frame
+        wire.remove(2);
+
+        let mut receiver = Packager::with_std_messages();
+        receiver.set_framing(Some(Framing::Cobs));
+        let mut msgs = Vec::new();
+        for chunk in wire.chunks(3) {
+            receiver.push_bytes(chunk).unwrap();
+            msgs.extend(receiver.drain_messages());
+        }
+
+        assert_eq!(msgs.len(), 3);
+        assert!(msgs[0].is_err());
+        assert_eq!(msgs[1].as_ref().unwrap().downcast_ref::<AdcData>(), Some(&adc));
+        assert!(msgs[2].as_ref().unwrap().is::<Ping>());
+        assert!(receiver.is_empty());
+
+        // the start of a frame is taken out of the stream and held until
+        // the rest of it arrives
+        sender.unpack(&status).unwrap();
+        let mut wire: Vec<u8> = Vec::new();
+        sender.write_to(&mut wire).unwrap();
+        receiver.push_bytes(&wire[..4]).unwrap();
+        assert!(matches!(receiver.pack().unwrap_err(), CerealError::Underflow { .. }));
+        assert!(receiver.is_empty());
+        receiver.push_bytes(&wire[4..]).unwrap();
+        assert_eq!(receiver.pack().unwrap().downcast_ref::<Status>(), Some(&status));
+    }
+
+    #[test]
+    fn cobs_frame_over_the_limit(){
+        use crate::frame::Framing;
+
+        let mut receiver = Packager::with_limit(16);
+        Message::register(&mut receiver);
+        receiver.set_framing(Some(Framing::Cobs));
+
+        // a frame with no delimiter is refused once it outgrows the
+        // limit, and the rest of it is dropped rather than held
+        let mut msgs = Vec::new();
+        for chunk in [0x11; 16000].chunks(16) {
+            receiver.push_bytes(chunk).unwrap();
+            msgs.extend(receiver.drain_messages());
+        }
+        assert_eq!(msgs.len(), 1);
+        assert_eq!(msgs[0].as_ref().unwrap_err(), &CerealError::LengthOverflow { len: 17, max: 16 });
+        assert!(receiver.is_empty());
+
+        let mut sender = Packager::new();
+        sender.set_framing(Some(Framing::Cobs));
+        sender.unpack(&Ping::default()).unwrap();
+        let mut wire = vec![0];
+        sender.write_to(&mut wire).unwrap();
+        receiver.push_bytes(&wire).unwrap();
+        assert!(receiver.pack().unwrap().is::<Ping>());
+        assert!(receiver.is_empty());
+    }
+
+    #[test]
+    fn json_round_trip(){
+        use crate::json::JsonRegistry;
+
+        let mut registry = JsonRegistry::new();
+        registry.add::<Ping>();
+        registry.add::<VersionData>();
+        registry.add::<AdcData>();
+        registry.add::<SerialParams>();
+
+        let adc = AdcData{ channel: 7, data: vec![1024, 1999, 0, -800, -900] };
+        assert_eq!(
+            registry.to_json_string(&adc).unwrap(),
+            r#"{"AdcData":{"channel":7,"data":[1024,1999,0,-800,-900]}}"#
+        );
+
+        let fixtures = [
+            r#"{"Ping":{}}"#,
+            r#"{"VersionData":{"major":1,"minor":2,"maintenance":3,"build":4}}"#,
+            r#"{"AdcData":{"channel":7,"data":[1024,1999,0,-800,-900]}}"#,
+            r#"{"8":{"channel":1,"baud":19200,"char_len":8,"parity":1,"stop":1}}"#,
+        ];
+        let mut packager = Packager::new();
+        for fixture in fixtures {
+            let msg = registry.from_json_str(fixture).unwrap();
+            packager.unpack(msg.as_ref()).unwrap();
+        }
+
+        let mut wire: Vec<u8> = Vec::new();
+        packager.write_to(&mut wire).unwrap();
+        assert_eq!(wire, [
+            1,
+            4, 1, 2, 3, 4,
+            6, 7, 10, 0, 0, 4, 207, 7, 0, 0, 224, 252, 124, 252,
+            8, 1, 0, 75, 0, 0, 8, 1, 1,
+        ]);
+
+        assert_eq!(
+            registry.from_json_str(r#"{"Pong":{}}"#).unwrap_err(),
+            CerealError::InvalidFormat(String::from("unknown box name Pong"))
+        );
+        assert_eq!(registry.to_json(&Pong::default()), Err(CerealError::UnknownId { id: 2 }));
+    }
+
+    #[test]
+    fn scribble(){
+        let bytes = b"\x08\x09\xA0\xA1 hello".to_vec();
+        let le_u32 = u32::from_le_bytes((&bytes[0..4]).try_into().unwrap());
+
+        assert_eq!(0xa1a00908, le_u32);
+
+        let input_data: Vec<i16> = vec![100, 200, 300];
+
+        let result_bytes: Vec<u8> = input_data
+            .iter()
+            .flat_map(|&value| value.to_le_bytes().to_vec())
+            .collect();
+
+        assert_eq!(result_bytes, [100, 0, 200, 0, 44, 1]);
+    }
+
+    /// Checks that the enum and the box of a message pour out the same bytes,
+    /// and that both of them pour back in to the same message.
+    fn check_message(msg: Message, expected: &[u8]) {
+        let mut serial = CerealStream::new();
+        msg.serialize(&mut serial).unwrap();
+        assert_eq!(expected, serial.get_vec());
+
+        let mut packager = Packager::new();
+        Message::register(&mut packager);
+        packager.unpack(msg.as_box()).unwrap();
+        let mut wire: Vec<u8> = Vec::new();
+        packager.write_to(&mut wire).unwrap();
+        assert_eq!(expected, wire);
+
+        packager.push_bytes(&wire).unwrap();
+        assert_eq!(Message::from_box(packager.pack().unwrap()).unwrap(), msg);
+
+        let rx_message = Message::deserialize(&mut serial);
+        assert_eq!(rx_message.unwrap(), msg);
+        assert!(serial.is_empty());
+    }
+
+    #[test]
+    fn check_ping_pong(){
+        check_message(Message::Ping(Ping{}), &[1]);
+        check_message(Message::Pong(Pong{}), &[2]);
+    }
+
+    #[test]
+    fn check_version(){
+        check_message(Message::VersionQuery(VersionQuery{}), &[3]);
+        check_message(
+            VersionData{ major: 1, minor: 2, maintenance: 3, build: 4 }.into(),
+            &[4, 1, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn check_adc(){
+        check_message(
+            AdcQuery{ channel: 1, length: 100, increment_usec: 5 }.into(),
+            &[5, 1, 100, 5, 0, 0, 0]
+        );
+        check_message(
+            AdcData{ channel: 1, data: [1024, 1999, 0, -800, -900].to_vec() }.into(),
+            &[6, 1, 10, 0, 0, 4, 207, 7, 0, 0, 224, 252, 124, 252]
+        );
+    }
+
+    #[test]
+    fn adc_data_too_long(){
+        let mut packager = Packager::new();
+        let adc = AdcData{ channel: 1, data: vec![0; 40_000] };
+        assert_eq!(
+            packager.unpack(&adc),
+            Err(CerealError::LengthOverflow { len: 80_000, max: 65_535 })
+        );
+        assert!(packager.is_empty());
+    }
+
+    #[test]
+    fn check_status(){
+        check_message(
+            Status{ rcv_count: 260, snd_count: 270, rcv_fails: 0 }.into(),
+            &[7, 4, 1, 14, 1, 0, 0]
+        );
+    }
+
+    #[test]
+    fn check_parms(){
+        check_message(
+            SerialParams{
+                channel: 1,
+                baud: 19200,
+                char_len: CharLength::Eight,
+                parity: Parity::Even,
+                stop: StopBits::One,
+            }.into(),
+            &[8, 1, 0, 75, 0, 0, 8, 1, 1]
+        );
+    }
+
+    #[test]
+    fn invalid_parms(){
+        let mut packager = Packager::with_std_messages();
+        packager.push_bytes(&[8, 1, 0, 75, 0, 0, 99, 99, 99]).unwrap();
+        assert_eq!(
+            packager.pack().unwrap_err().root(),
+            &CerealError::InvalidEnumValue { name: "CharLength", value: 99 }
+        );
+
+        let mut packager = Packager::with_std_messages();
+        packager.push_bytes(&[8, 1, 0, 75, 0, 0, 8, 1, 9]).unwrap();
+        assert_eq!(
+            packager.pack().unwrap_err().root(),
+            &CerealError::InvalidEnumValue { name: "StopBits", value: 9 }
+        );
+    }
+
+    #[test]
+    fn line_settings_round_trip(){
+        for settings in ["8N1", "8E2", "7O1.5", "7N2", "8O1"] {
+            let sp = SerialParams::from_str(3, &format!("4800:{}", settings)).unwrap();
+            let byte = sp.line_settings().get_byte();
+            let mut copy = SerialParams{ channel: 3, baud: 4800, ..Default::default() };
+            copy.set_line_settings(LineSettings::from_byte(&byte).unwrap());
+            assert_eq!(copy, sp);
+        }
+    }
+
+    #[test]
+    fn check_serial_data(){
+        check_message(
+            SerialData{ channel: 2, data: String::from("AT\r") }.into(),
+            &[9, 2, 3, 0, b'A', b'T', b'\r']
+        );
+
+        let mut serial = CerealStream::new();
+        serial.push_bytes(&[9, 2, 2, 0, 0xc3, 0x28]).unwrap();
+        assert_eq!(Message::deserialize(&mut serial), Err(CerealError::InvalidUtf8));
+        assert_eq!(serial.len(), 6);
+    }
+
+    #[test]
+    fn check_multiple_deserialize(){
+        let mut serial = CerealStream::new();
+
+        let params = Message::SerialParams(SerialParams{
+            channel: 1,
+            baud: 19200,
+            char_len: CharLength::Eight,
+            parity: Parity::Even,
+            stop: StopBits::One,
+        });
+        let response = Message::AdcData(AdcData{
+            channel: 1,
+            data: [1024, 1999, 0, -800, -900].to_vec(),
+        });
+
+        params.serialize(&mut serial).unwrap();
+        response.serialize(&mut serial).unwrap();
+        assert_eq!(
+            [8, 1, 0, 75, 0, 0, 8, 1, 1, 6, 1, 10, 0, 0, 4, 207, 7, 0, 0, 224, 252, 124, 252],
+            serial.get_vec()[..]
+        );
+
+        assert_eq!(Message::deserialize(&mut serial).unwrap(), params);
+        assert_eq!(
+            [6, 1, 10, 0, 0, 4, 207, 7, 0, 0, 224, 252, 124, 252],
+            serial.get_vec()[..]
+        );
+        assert_eq!(Message::deserialize(&mut serial).unwrap(), response);
+
+        serial.push_bytes(&[42]).unwrap();
+        assert_eq!(Message::deserialize(&mut serial), Err(CerealError::UnknownId { id: 42 }));
+    }
 }
@@ -1,4 +1,20 @@
+use std::any::Any;
 use std::collections::HashMap;
+use std::fmt;
+use std::io;
+
+use bytes::{Buf, Bytes, BytesMut};
+
+use super::bits::{BitOrder, BitReader, BitWriter};
+use super::cobs::CobsDecoder;
+use super::error::CerealError;
+use super::frame::Framing;
+pub use super::field::CerealField;
+pub use super::reader::{CerealRead, CerealReader};
+
+/// Derives [`CerealBox`] for a struct from its list of fields.
+#[cfg(feature = "derive")]
+pub use open_channel_derive::CerealBox;
 
 /// A Hub for packing and unpacking Cereal Boxes into a Cereal Stream
 ///
@@ -10,7 +26,7 @@ use std::collections::HashMap;
 ///   let mut stream = CerealStream::new();
 ///   assert_eq!(stream.is_empty(), true);
 ///
-///   stream.push_bytes(&[1,2,3]);
+///   stream.push_bytes(&[1,2,3]).unwrap();
 ///   assert_eq!(stream.is_empty(), false);
 ///
 ///   let out = stream.pop_bytes(2);
@@ -20,23 +36,254 @@ use std::collections::HashMap;
 ///   let out = stream.pop_byte();
 ///   assert_eq!(out, 3);
 ///   assert_eq!(stream.is_empty(), true);
+///
+///   stream.push_bytes(&[4,5,6]).unwrap();
+///   let head = stream.split_to(2);
+///   assert_eq!(&head[..], &[4,5]);
+///   assert_eq!(stream.freeze(), [6].as_ref());
+/// ```
+pub struct CerealStream {
+    buf: BytesMut,
+    // bytes at the front of `buf` that have been read but are kept
+    // around because a mark may still roll back over them
+    pos: usize,
+    marks: Vec<usize>,
+    endian: Endian,
+    limit: Option<usize>,
+}
+
+/// The width of the length written in front of a string or byte blob.
+///
+/// The length counts bytes and uses the default byte order of the stream.
+///
+/// # Examples
+///
+/// ```
+///   use open_channel::cereal::{CerealRead, CerealStream, LengthPrefix};
+///   use open_channel::error::CerealError;
+///
+///   let mut stream = CerealStream::new();
+///   stream.push_str("hi", LengthPrefix::U16).unwrap();
+///   stream.push_blob(&[1, 2, 3], LengthPrefix::Varint).unwrap();
+///   assert_eq!(stream.get_vec(), &[2, 0, b'h', b'i', 3, 1, 2, 3]);
+///
+///   assert_eq!(stream.try_pop_string(LengthPrefix::U16, 16), Ok(String::from("hi")));
+///   assert_eq!(
+///       stream.try_pop_blob(LengthPrefix::Varint, 2),
+///       Err(CerealError::LengthOverflow { len: 3, max: 2 })
+///   );
+///   assert_eq!(stream.try_pop_blob(LengthPrefix::Varint, 16), Ok(vec![1, 2, 3]));
+///
+///   assert_eq!(
+///       stream.push_blob(&[0; 256], LengthPrefix::U8),
+///       Err(CerealError::LengthOverflow { len: 256, max: 255 })
+///   );
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LengthPrefix {
+    U8,
+    U16,
+    U32,
+    Varint,
+}
+
+impl LengthPrefix {
+    /// Returns the longest length this prefix can describe.
+    pub fn max_len(&self) -> usize {
+        match self {
+            Self::U8 => u8::MAX as usize,
+            Self::U16 => u16::MAX as usize,
+            Self::U32 => u32::MAX as usize,
+            Self::Varint => usize::MAX,
+        }
+    }
+}
+
+/// The byte order used for multi-byte values in a [`CerealStream`].
+///
+/// # Examples
+///
 /// ```
-pub struct CerealStream(Vec<u8>);
+///   use open_channel::cereal::{CerealRead, CerealStream, Endian};
+///
+///   let mut stream = CerealStream::with_endian(Endian::Big);
+///   stream.push_u16(0x0102).unwrap();
+///   stream.push_u16_le(0x0102).unwrap();
+///   assert_eq!(stream.get_vec(), &[1, 2, 2, 1]);
+///
+///   assert_eq!(stream.try_pop_u16_le(), Ok(0x0201));
+///   assert_eq!(stream.try_pop_u16(), Ok(0x0201));
+///
+///   stream.push_f32_be(1.5).unwrap();
+///   stream.push_i64_le(-2).unwrap();
+///   stream.push_bool(true).unwrap();
+///   assert_eq!(stream.try_pop_f32_be(), Ok(1.5));
+///   assert_eq!(stream.try_pop_i64_le(), Ok(-2));
+///   assert_eq!(stream.try_pop_bool(), Ok(true));
+///   assert!(stream.try_pop_u32().is_err());
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Endian {
+    /// Least significant byte first, as used by the STM32.
+    #[default]
+    Little,
+    /// Most significant byte first.
+    Big,
+}
+
+/// Generates the default order, little endian and big endian
+/// push methods for a primitive type.
+macro_rules! push_methods {
+    ($($ty:ty: $push:ident, $push_le:ident, $push_be:ident;)*) => {
+        $(
+            #[doc = concat!("pushes a `", stringify!($ty), "` in the default byte order of the stream.")]
+            ///
+            /// # Errors
+            ///
+            /// Returns [`CerealError::Overflow`] if the stream is too full.
+            pub fn $push(&mut self, value: $ty) -> Result<(), CerealError> {
+                match self.endian {
+                    Endian::Little => self.$push_le(value),
+                    Endian::Big => self.$push_be(value),
+                }
+            }
+
+            #[doc = concat!("pushes a little endian `", stringify!($ty), "`.")]
+            ///
+            /// # Errors
+            ///
+            /// Returns [`CerealError::Overflow`] if the stream is too full.
+            pub fn $push_le(&mut self, value: $ty) -> Result<(), CerealError> {
+                self.push_bytes(&value.to_le_bytes())
+            }
+
+            #[doc = concat!("pushes a big endian `", stringify!($ty), "`.")]
+            ///
+            /// # Errors
+            ///
+            /// Returns [`CerealError::Overflow`] if the stream is too full.
+            pub fn $push_be(&mut self, value: $ty) -> Result<(), CerealError> {
+                self.push_bytes(&value.to_be_bytes())
+            }
+        )*
+    };
+}
 
 impl CerealStream {
     /// Creates a new [`CerealStream`].
     pub fn new() -> Self {
-        Self(Vec::new())
+        Self::with_endian(Endian::default())
+    }
+
+    /// Creates a new [`CerealStream`] with room for `capacity` bytes
+    /// before it needs to reallocate.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buf: BytesMut::with_capacity(capacity),
+            pos: 0,
+            marks: Vec::new(),
+            endian: Endian::default(),
+            limit: None,
+        }
+    }
+
+    /// Creates a new [`CerealStream`] that refuses to hold more than
+    /// `limit` unread bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///   use open_channel::cereal::CerealStream;
+    ///   use open_channel::error::CerealError;
+    ///
+    ///   let mut stream = CerealStream::with_limit(4);
+    ///   stream.push_u16(1).unwrap();
+    ///   assert_eq!(stream.free_space(), 2);
+    ///
+    ///   assert_eq!(stream.push_u32(1), Err(CerealError::Overflow {
+    ///       requested: 4,
+    ///       free: 2,
+    ///   }));
+    ///   assert_eq!(stream.len(), 2);
+    /// ```
+    pub fn with_limit(limit: usize) -> Self {
+        let mut stream = Self::with_capacity(limit);
+        stream.limit = Some(limit);
+        stream
+    }
+
+    /// Returns the most unread bytes this [`CerealStream`] will hold, if
+    /// it is bounded.
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// Sets or clears the most unread bytes this [`CerealStream`] will hold.
+    ///
+    /// Bytes already in the stream are kept even if there are more of
+    /// them than the new limit.
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+    }
+
+    /// Returns how many more bytes can be pushed before the stream is full.
+    ///
+    /// An unbounded stream always reports `usize::MAX`.
+    pub fn free_space(&self) -> usize {
+        match self.limit {
+            Some(limit) => limit.saturating_sub(self.len()),
+            None => usize::MAX,
+        }
+    }
+
+    /// Makes room for at least `additional` more bytes, so the pushes that
+    /// follow do not reallocate.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Overflow`] if the bytes would take a bounded
+    /// stream past its limit. Nothing is reserved in that case.
+    pub fn reserve(&mut self, additional: usize) -> Result<(), CerealError> {
+        self.ensure_free(additional)?;
+        self.buf.reserve(additional);
+        Ok(())
+    }
+
+    /// Creates a new [`CerealStream`] whose typed readers and writers
+    /// default to the given byte order.
+    pub fn with_endian(endian: Endian) -> Self {
+        Self {
+            buf: BytesMut::new(),
+            pos: 0,
+            marks: Vec::new(),
+            endian,
+            limit: None,
+        }
+    }
+
+    /// Returns the default byte order of this [`CerealStream`].
+    pub fn endian(&self) -> Endian {
+        self.endian
     }
 
-    /// Returns a reference to the get vec of this [`CerealStream`].
-    pub fn get_vec(&self) -> &Vec<u8> {
-        &self.0
+    /// Sets the default byte order of this [`CerealStream`].
+    pub fn set_endian(&mut self, endian: Endian) {
+        self.endian = endian;
+    }
+
+    /// Returns a view of the unread bytes of this [`CerealStream`].
+    pub fn get_vec(&self) -> &[u8] {
+        &self.buf[self.pos..]
+    }
+
+    /// Returns the number of unread bytes in the [`CerealStream`].
+    pub fn len(&self) -> usize {
+        self.buf.len() - self.pos
     }
 
     /// Returns true if the stream is empty [`CerealStream`].
     pub fn is_empty(&self) -> bool {
-        self.get_vec().len() == 0
+        self.len() == 0
     }
 
     /// Returns the a singel byte from the stream.
@@ -45,7 +292,9 @@ impl CerealStream {
     ///
     /// Panics if the stream is empty.
     pub fn pop_byte(&mut self) -> u8 {
-        self.0.drain(0..1).next().unwrap()
+        let byte = self.get_vec()[0];
+        self.advance(1);
+        byte
     }
 
     /// Returns the specified number of bytes from the stream.
@@ -54,12 +303,257 @@ impl CerealStream {
     ///
     /// Panics if the stream does not conatin enough bytes.
     pub fn pop_bytes(&mut self, num_bytes: usize) -> Vec<u8> {
-        self.0.drain(0..num_bytes).collect()
+        let out = self.get_vec()[..num_bytes].to_vec();
+        self.advance(num_bytes);
+        out
+    }
+
+    /// Splits the first `num_bytes` off the front of the stream and hands
+    /// them over as an immutable [`Bytes`] without copying.
+    ///
+    /// While a mark is outstanding the bytes are copied instead, so that
+    /// they can still be rolled back.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stream does not conatin enough bytes.
+    pub fn split_to(&mut self, num_bytes: usize) -> Bytes {
+        if self.marks.is_empty() {
+            return self.buf.split_to(num_bytes).freeze();
+        }
+        let out = Bytes::copy_from_slice(&self.get_vec()[..num_bytes]);
+        self.advance(num_bytes);
+        out
+    }
+
+    /// Consumes the stream and returns the unread bytes as [`Bytes`].
+    pub fn freeze(mut self) -> Bytes {
+        self.buf.advance(self.pos);
+        self.buf.freeze()
+    }
+
+    /// pushes a `u8`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Overflow`] if the stream is full.
+    pub fn push_u8(&mut self, value: u8) -> Result<(), CerealError> {
+        self.push_bytes(&[value])
+    }
+
+    /// pushes an `i8`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Overflow`] if the stream is full.
+    pub fn push_i8(&mut self, value: i8) -> Result<(), CerealError> {
+        self.push_bytes(&value.to_le_bytes())
+    }
+
+    /// pushes a `bool` as a single 0 or 1 byte.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Overflow`] if the stream is full.
+    pub fn push_bool(&mut self, value: bool) -> Result<(), CerealError> {
+        self.push_u8(value as u8)
+    }
+
+    /// pushes a `u64` as an unsigned LEB128 varint, using one byte for
+    /// every seven significant bits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///   use open_channel::cereal::{CerealRead, CerealStream};
+    ///
+    ///   let mut stream = CerealStream::new();
+    ///   stream.push_varint_u64(5).unwrap();
+    ///   stream.push_varint_u64(300).unwrap();
+    ///   stream.push_varint_i64(-3).unwrap();
+    ///   assert_eq!(stream.get_vec(), &[5, 0xac, 0x02, 5]);
+    ///
+    ///   assert_eq!(stream.try_pop_varint_u64(), Ok(5));
+    ///   assert_eq!(stream.try_pop_varint_u64(), Ok(300));
+    ///   assert_eq!(stream.try_pop_varint_i64(), Ok(-3));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Overflow`] if the stream is too full.
+    /// Nothing is pushed in that case.
+    pub fn push_varint_u64(&mut self, mut value: u64) -> Result<(), CerealError> {
+        let mut bytes = [0; 10];
+        let mut len = 0;
+        while value >= 0x80 {
+            bytes[len] = value as u8 | 0x80;
+            value >>= 7;
+            len += 1;
+        }
+        bytes[len] = value as u8;
+        self.push_bytes(&bytes[..=len])
+    }
+
+    /// pushes an `i64` as a zigzag encoded LEB128 varint, so that values
+    /// close to zero are short whatever their sign.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Overflow`] if the stream is too full.
+    /// Nothing is pushed in that case.
+    pub fn push_varint_i64(&mut self, value: i64) -> Result<(), CerealError> {
+        self.push_varint_u64(((value << 1) ^ (value >> 63)) as u64)
+    }
+
+    /// pushes a byte blob preceded by its length.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::LengthOverflow`] if the blob is too long for
+    /// the prefix, or [`CerealError::Overflow`] if the stream is too full.
+    /// Nothing is pushed in either case.
+    pub fn push_blob(&mut self, bytes: &[u8], prefix: LengthPrefix) -> Result<(), CerealError> {
+        let len = bytes.len();
+        if len > prefix.max_len() {
+            return Err(CerealError::LengthOverflow { len, max: prefix.max_len() });
+        }
+        let mut head = CerealStream::with_endian(self.endian);
+        match prefix {
+            LengthPrefix::U8 => head.push_u8(len as u8)?,
+            LengthPrefix::U16 => head.push_u16(len as u16)?,
+            LengthPrefix::U32 => head.push_u32(len as u32)?,
+            LengthPrefix::Varint => head.push_varint_u64(len as u64)?,
+        }
+        self.ensure_free(head.len() + len)?;
+        self.push_bytes(head.get_vec())?;
+        self.push_bytes(bytes)
+    }
+
+    /// pushes a UTF-8 string preceded by its length in bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::LengthOverflow`] if the string is too long
+    /// for the prefix, or [`CerealError::Overflow`] if the stream is too
+    /// full. Nothing is pushed in either case.
+    pub fn push_str(&mut self, value: &str, prefix: LengthPrefix) -> Result<(), CerealError> {
+        self.push_blob(value.as_bytes(), prefix)
+    }
+
+    push_methods! {
+        u16: push_u16, push_u16_le, push_u16_be;
+        u32: push_u32, push_u32_le, push_u32_be;
+        u64: push_u64, push_u64_le, push_u64_be;
+        i16: push_i16, push_i16_le, push_i16_be;
+        i32: push_i32, push_i32_le, push_i32_be;
+        i64: push_i64, push_i64_le, push_i64_be;
+        f32: push_f32, push_f32_le, push_f32_be;
+        f64: push_f64, push_f64_le, push_f64_be;
+    }
+
+    /// consumes `num_bytes` of the unread bytes.
+    fn advance(&mut self, num_bytes: usize) {
+        assert!(num_bytes <= self.len(), "advanced past the end of the stream");
+        self.pos += num_bytes;
+        self.release();
+    }
+
+    /// drops the bytes that have been read, unless a mark still needs them.
+    fn release(&mut self) {
+        if self.marks.is_empty() {
+            self.buf.advance(self.pos);
+            self.pos = 0;
+        }
+    }
+
+    /// Returns a [`BitWriter`] that packs bit fields into the stream.
+    pub fn bit_writer(&mut self, order: BitOrder) -> BitWriter<'_> {
+        BitWriter::new(self, order)
+    }
+
+    /// Returns a [`BitReader`] that unpacks bit fields from the stream.
+    pub fn bit_reader(&mut self, order: BitOrder) -> BitReader<'_, Self> {
+        BitReader::new(self, order)
     }
 
     /// pushes the specified bytes into the stream.
-    pub fn push_bytes(&mut self, bytes: &[u8]) {
-        self.0.extend(bytes)
+    ///
+    /// Space freed by earlier pops is reclaimed before the buffer grows,
+    /// so a stream that is drained as fast as it is filled stays at a
+    /// constant size.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Overflow`] if the bytes would take a bounded
+    /// stream past its limit. Nothing is pushed in that case.
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> Result<(), CerealError> {
+        self.ensure_free(bytes.len())?;
+        self.buf.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn ensure_free(&self, num_bytes: usize) -> Result<(), CerealError> {
+        let free = self.free_space();
+        if num_bytes > free {
+            return Err(CerealError::Overflow { requested: num_bytes, free });
+        }
+        Ok(())
+    }
+}
+
+impl CerealRead for CerealStream {
+    fn remaining(&self) -> &[u8] {
+        self.get_vec()
+    }
+
+    fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    fn skip(&mut self, num_bytes: usize) -> Result<(), CerealError> {
+        self.peek_bytes(num_bytes)?;
+        self.advance(num_bytes);
+        Ok(())
+    }
+
+    fn mark(&mut self) {
+        self.marks.push(self.pos);
+    }
+
+    fn rollback(&mut self) {
+        if let Some(pos) = self.marks.pop() {
+            self.pos = pos;
+            self.release();
+        }
+    }
+
+    fn commit(&mut self) {
+        if self.marks.pop().is_some() {
+            self.release();
+        }
+    }
+}
+
+/// Pushes as many of the written bytes as the stream has room for.
+impl io::Write for CerealStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let num_bytes = buf.len().min(self.free_space());
+        self.buf.extend_from_slice(&buf[..num_bytes]);
+        Ok(num_bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Pops as many bytes as fit into the read buffer.
+impl io::Read for CerealStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let num_bytes = buf.len().min(self.len());
+        buf[..num_bytes].copy_from_slice(&self.get_vec()[..num_bytes]);
+        self.advance(num_bytes);
+        Ok(num_bytes)
     }
 }
 
@@ -69,33 +563,367 @@ impl Default for CerealStream {
     }
 }
 
+/// The firmware version a peer reports, ordered by major, minor,
+/// maintenance and then build number.
+///
+/// # Examples
+///
+/// ```
+///   use open_channel::cereal::FirmwareVersion;
+///
+///   let old = FirmwareVersion::new(1, 0, 9, 3);
+///   assert!(old < FirmwareVersion::new(1, 1, 0, 0));
+/// ```
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+pub struct FirmwareVersion {
+    pub major: u8,
+    pub minor: u8,
+    pub maintenance: u8,
+    pub build: u8,
+}
+
+impl FirmwareVersion {
+    /// Creates a new [`FirmwareVersion`].
+    pub const fn new(major: u8, minor: u8, maintenance: u8, build: u8) -> Self {
+        Self {
+            major,
+            minor,
+            maintenance,
+            build,
+        }
+    }
+}
+
 /// A trait representing a Cereal Box.
 ///
 /// This trait defines common methods that all Cereal Boxes should implement,
 /// A cereal box is a metaphor for a Message struct that can be poured out
-/// into a binary stream and tehn poured back into box from the stream and
-/// then consumed
-pub trait CerealBox {
+/// into a binary stream and tehn poured back into box from the stream.
+/// What happens to a box once it is packed is up to the handlers
+/// registered with [`Packager::on`].
+///
+/// Boxes are [`Send`] so a packed message can be handed to another thread.
+pub trait CerealBox: Any + Send {
     /// Get the type id fo the ceral being processed.
     fn get_id(&self) -> u8;
 
-    /// Pour a cereal stream into a box.
+    /// Pour a cereal stream, or any other [`CerealRead`] source, into a box.
     ///
     /// # Errors
     ///
     /// This function will return an error if the stream lack sufficient
     /// bytes to fill the box.
-    fn pour_in(&mut self, _: &mut CerealStream) -> Result<(), String>;
+    fn pour_in(&mut self, _: &mut dyn CerealRead) -> Result<(), CerealError>;
 
     /// Pour the contents of a cereal box into a cereal stream.
-    fn pour_out(&self, _: &mut CerealStream) {
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the stream has no room for
+    /// the contents, or a field can not be represented on the wire.
+    fn pour_out(&self, _: &mut CerealStream) -> Result<(), CerealError> {
+        Ok(())
+    }
+
+    /// The number of bytes every box of this flavor takes on the wire, not
+    /// counting the id byte, or `None` if it depends on the contents.
+    ///
+    /// A [`Packager`] refuses to pack a fixed size box until all of its
+    /// bytes have arrived.
+    fn fixed_len(&self) -> Option<usize> {
+        None
+    }
+
+    /// The number of bytes this box takes on the wire, not counting the
+    /// id byte.
+    ///
+    /// Defaults to [`CerealBox::fixed_len`], or else to pouring the box
+    /// into a scratch stream and counting. Variable sized boxes can
+    /// override it to skip the scratch copy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///   use open_channel::cereal::{CerealBox, CerealRead, CerealStream};
+    ///   use open_channel::error::CerealError;
+    ///
+    ///   struct Samples(Vec<i16>);
+    ///
+    ///   impl CerealBox for Samples {
+    ///       fn get_id(&self) -> u8 {
+    ///           6
+    ///       }
+    ///
+    ///       fn pour_out(&self, package: &mut CerealStream) -> Result<(), CerealError> {
+    ///           for &sample in &self.0 {
+    ///               package.push_i16(sample)?;
+    ///           }
+    ///           Ok(())
+    ///       }
+    ///
+    ///       fn pour_in(&mut self, _: &mut dyn CerealRead) -> Result<(), CerealError> {
+    ///           Ok(())
+    ///       }
+    ///   }
+    ///
+    ///   assert_eq!(Samples(vec![1, 2, 3]).fixed_len(), None);
+    ///   assert_eq!(Samples(vec![1, 2, 3]).encoded_len(), Ok(6));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the box can not be poured
+    /// out.
+    fn encoded_len(&self) -> Result<usize, CerealError> {
+        if let Some(len) = self.fixed_len() {
+            return Ok(len);
+        }
+        let mut scratch = CerealStream::new();
+        self.pour_out(&mut scratch)?;
+        Ok(scratch.len())
+    }
+
+    /// The layout version [`CerealBox::pour_in`] and
+    /// [`CerealBox::pour_out`] use.
+    ///
+    /// Bump it when the wire layout of the box changes, and keep the older
+    /// layouts working through [`CerealBox::pour_in_layout`] and
+    /// [`CerealBox::pour_out_layout`].
+    fn layout_version(&self) -> u8 {
+        0
+    }
+
+    /// The layout version a peer running `peer` firmware uses for this
+    /// box. Defaults to the current layout.
+    fn layout_for_peer(&self, _peer: &FirmwareVersion) -> u8 {
+        self.layout_version()
+    }
+
+    /// Pour a box laid out as `layout` into this box.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::UnsupportedLayout`] if `layout` is not the
+    /// current one. Boxes with older layouts override this.
+    fn pour_in_layout(&mut self, layout: u8, package: &mut dyn CerealRead) -> Result<(), CerealError> {
+        if layout != self.layout_version() {
+            return Err(CerealError::UnsupportedLayout { layout });
+        }
+        self.pour_in(package)
     }
 
-    /// defines how a box of cereal is consumed.
-    fn consume(&self){
+    /// Pour this box out laid out as `layout`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::UnsupportedLayout`] if `layout` is not the
+    /// current one. Boxes with older layouts override this.
+    fn pour_out_layout(&self, layout: u8, package: &mut CerealStream) -> Result<(), CerealError> {
+        if layout != self.layout_version() {
+            return Err(CerealError::UnsupportedLayout { layout });
+        }
+        self.pour_out(package)
+    }
+
+    /// Whether frames of this box carry their length, so that fields
+    /// added by newer firmware can be skipped by older hosts.
+    ///
+    /// A [`Packager`] writes an extensible box as its id, the length of
+    /// its contents as a [`LengthPrefix::Varint`] and then the contents.
+    /// The box is poured in from just those contents: any bytes it does
+    /// not read are skipped, and trailing `Option` fields it does read
+    /// are `None` when the frame ends before them. A length longer than a
+    /// bounded packager can hold fails with [`CerealError::LengthOverflow`]
+    /// instead of being waited for.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///   use open_channel::cereal::{CerealBox, CerealField, CerealRead, CerealStream, Packager};
+    ///   use open_channel::error::CerealError;
+    ///
+    ///   #[derive(Default)]
+    ///   struct Status {
+    ///       rcv_count: u16,
+    ///       rcv_fails: Option<u16>,
+    ///   }
+    ///
+    ///   impl CerealBox for Status {
+    ///       fn get_id(&self) -> u8 {
+    ///           7
+    ///       }
+    ///
+    ///       fn extensible(&self) -> bool {
+    ///           true
+    ///       }
+    ///
+    ///       fn pour_out(&self, package: &mut CerealStream) -> Result<(), CerealError> {
+    ///           self.rcv_count.pour_out_field(package)?;
+    ///           self.rcv_fails.pour_out_field(package)
+    ///       }
+    ///
+    ///       fn pour_in(&mut self, package: &mut dyn CerealRead) -> Result<(), CerealError> {
+    ///           self.rcv_count = u16::pour_in_field(package)?;
+    ///           self.rcv_fails = Option::pour_in_field(package)?;
+    ///           Ok(())
+    ///       }
+    ///   }
+    ///
+    ///   let mut packager = Packager::new();
+    ///   packager.add_flavor::<Status>();
+    ///
+    ///   // an older peer, without rcv_fails
+    ///   packager.push_bytes(&[7, 2, 5, 0]).unwrap();
+    ///   // a newer peer, with a field this host does not know about
+    ///   packager.push_bytes(&[7, 6, 5, 0, 1, 0, 0xff, 0xff]).unwrap();
+    ///
+    ///   let old = packager.pack().unwrap();
+    ///   assert_eq!(old.downcast_ref::<Status>().unwrap().rcv_fails, None);
+    ///   let new = packager.pack().unwrap();
+    ///   assert_eq!(new.downcast_ref::<Status>().unwrap().rcv_fails, Some(1));
+    ///   assert!(packager.is_empty());
+    /// ```
+    fn extensible(&self) -> bool {
+        false
+    }
+
+    /// The firmware version this box announces for the peer that sent it,
+    /// if it is the version reply of the link.
+    ///
+    /// A [`Packager`] that packs a box with a peer version lays out the
+    /// boxes that follow, in both directions, for that version.
+    fn peer_version(&self) -> Option<FirmwareVersion> {
+        None
+    }
+}
+
+impl dyn CerealBox {
+    /// Returns true if the box is a `T`.
+    pub fn is<T: CerealBox>(&self) -> bool {
+        (self as &dyn Any).is::<T>()
+    }
+
+    /// Returns the box as a `T`, or `None` if it is another flavor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///   use open_channel::cereal::{CerealBox, CerealRead};
+    ///   use open_channel::error::CerealError;
+    ///
+    ///   struct Ping;
+    ///
+    ///   impl CerealBox for Ping {
+    ///       fn get_id(&self) -> u8 {
+    ///           1
+    ///       }
+    ///
+    ///       fn pour_in(&mut self, _: &mut dyn CerealRead) -> Result<(), CerealError> {
+    ///           Ok(())
+    ///       }
+    ///   }
+    ///
+    ///   let msg: Box<dyn CerealBox> = Box::new(Ping);
+    ///   assert!(msg.is::<Ping>());
+    ///   assert!(msg.downcast_ref::<Ping>().is_some());
+    ///   assert!(msg.downcast::<Ping>().is_ok());
+    /// ```
+    pub fn downcast_ref<T: CerealBox>(&self) -> Option<&T> {
+        (self as &dyn Any).downcast_ref::<T>()
+    }
+
+    /// Returns the box as a mutable `T`, or `None` if it is another
+    /// flavor.
+    pub fn downcast_mut<T: CerealBox>(&mut self) -> Option<&mut T> {
+        (self as &mut dyn Any).downcast_mut::<T>()
+    }
+
+    /// Takes the `T` out of an owned box.
+    ///
+    /// # Errors
+    ///
+    /// Returns the box unchanged if it is another flavor.
+    pub fn downcast<T: CerealBox>(self: Box<Self>) -> Result<Box<T>, Box<dyn CerealBox>> {
+        if self.is::<T>() {
+            let any: Box<dyn Any> = self;
+            Ok(any.downcast::<T>().expect("type was checked"))
+        } else {
+            Err(self)
+        }
+    }
+}
+
+/// Implements [`CerealBox`] with the given id for a type that derives
+/// serde's `Serialize` and `Deserialize`, pouring it in and out with the
+/// [`ser`](crate::ser) and [`de`](crate::de) wire format.
+///
+/// # Examples
+///
+/// ```
+///   use open_channel::cereal::{CerealBox, CerealStream};
+///   use open_channel::serde_box;
+///   use serde_derive::{Deserialize, Serialize};
+///
+///   #[derive(Serialize, Deserialize, Default, PartialEq, Debug)]
+///   struct AdcQuery {
+///       channel: u8,
+///       length: u8,
+///       increment_usec: u32,
+///   }
+///
+///   serde_box!(AdcQuery, 5);
+///
+///   let query = AdcQuery { channel: 1, length: 100, increment_usec: 5 };
+///   let mut stream = CerealStream::new();
+///   query.pour_out(&mut stream).unwrap();
+///   assert_eq!(stream.get_vec(), &[1, 100, 5, 0, 0, 0]);
+///
+///   let mut copy = AdcQuery::default();
+///   copy.pour_in(&mut stream).unwrap();
+///   assert_eq!(copy, query);
+/// ```
+#[macro_export]
+macro_rules! serde_box {
+    ($ty:ty, $id:expr) => {
+        impl $crate::cereal::CerealBox for $ty {
+            fn get_id(&self) -> u8 {
+                $id
+            }
+
+            fn pour_out(
+                &self,
+                package: &mut $crate::cereal::CerealStream,
+            ) -> ::std::result::Result<(), $crate::error::CerealError> {
+                $crate::ser::to_stream(self, package)
+            }
+
+            fn pour_in(
+                &mut self,
+                package: &mut dyn $crate::cereal::CerealRead,
+            ) -> ::std::result::Result<(), $crate::error::CerealError> {
+                *self = $crate::de::from_reader(package)?;
+                ::std::result::Result::Ok(())
+            }
+        }
+    };
+}
+
+impl fmt::Debug for dyn CerealBox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CerealBox").field("id", &self.get_id()).finish()
     }
 }
 
+/// Builds a new, empty box of one flavor for [`Packager::pack`] to pour
+/// a frame into.
+pub type Factory = Box<dyn Fn() -> Box<dyn CerealBox> + Send>;
+
+/// Consumes every box of one flavor that [`Packager::pack`] packs.
+pub type Handler = Box<dyn FnMut(&dyn CerealBox) + Send>;
+
+/// The most bytes [`Packager::read_from`] takes in a single read.
+const READ_CHUNK: usize = 1024;
+
 /// A Hub for serializing and deserializing Cereal Flavors into a Package Stream
 ///
 /// # Examples
@@ -106,9 +934,19 @@ pub trait CerealBox {
 /// let mut packager = Packager::new();
 /// ```
 pub struct Packager {
-    map: HashMap<u8, Box<dyn CerealBox>>,
+    map: HashMap<u8, Factory>,
+    handlers: HashMap<u8, Handler>,
     stream: CerealStream,
-
+    // bytes that have left the front of the stream so far, so errors can
+    // report the offset a frame started at
+    offset: usize,
+    peer: Option<FirmwareVersion>,
+    framing: Option<Framing>,
+    // holds the start of a COBS frame across packs, so the bytes of a
+    // frame are only looked at once however they arrive
+    cobs: CobsDecoder,
+    // the offset the frame held by `cobs` started at
+    cobs_start: usize,
 }
 
 impl Packager {
@@ -117,48 +955,636 @@ impl Packager {
     pub fn new() -> Self {
         Self {
             map: HashMap::new(),
-            stream: CerealStream::new()
+            handlers: HashMap::new(),
+            stream: CerealStream::new(),
+            offset: 0,
+            peer: None,
+            framing: None,
+            cobs: CobsDecoder::new(),
+            cobs_start: 0,
+        }
+    }
+
+    /// Creates a new [`Packager`] whose stream refuses to hold more than
+    /// `limit` bytes, such as the size of the receive buffer on the far
+    /// side of the link.
+    ///
+    /// With [`Framing::Cobs`], a frame that decodes to more than `limit`
+    /// bytes fails with [`CerealError::LengthOverflow`] as soon as it
+    /// grows past it, and the rest of it is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///   use open_channel::cereal::Packager;
+    ///
+    ///   let mut packager = Packager::with_limit(4);
+    ///   packager.push_bytes(&[1, 2, 3]).unwrap();
+    ///   assert_eq!(packager.free_space(), 1);
+    ///   assert!(packager.push_bytes(&[4, 5]).is_err());
+    /// ```
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            map: HashMap::new(),
+            handlers: HashMap::new(),
+            stream: CerealStream::with_limit(limit),
+            offset: 0,
+            peer: None,
+            framing: None,
+            // a frame longer than the stream could hold is never kept
+            cobs: CobsDecoder::with_max_len(limit),
+            cobs_start: 0,
         }
     }
 
+    /// Returns how many more bytes the stream can take before it is full.
+    ///
+    /// An unbounded packager always reports `usize::MAX`.
+    pub fn free_space(&self) -> usize {
+        self.stream.free_space()
+    }
+
     /// Returns the true if the contained stream is empty.
     pub fn is_empty(&self) -> bool {
         self.stream.is_empty()
     }
 
-    /// Adds a Cereal Box to the Packager.
+    /// Returns the firmware version of the peer, if known.
+    pub fn peer_version(&self) -> Option<FirmwareVersion> {
+        self.peer
+    }
+
+    /// Sets the firmware version of the peer, so boxes are packed and
+    /// unpacked in the layouts it uses. `None` uses the current layout of
+    /// every box.
+    ///
+    /// This is done automatically when a box that reports a
+    /// [`CerealBox::peer_version`] is packed.
+    pub fn set_peer_version(&mut self, peer: Option<FirmwareVersion>) {
+        self.peer = peer;
+    }
+
+    /// Returns how frames are wrapped on the wire, if at all.
+    pub fn framing(&self) -> Option<Framing> {
+        self.framing
+    }
+
+    /// Sets how [`Packager::unpack`] wraps each frame and
+    /// [`Packager::pack`] expects to find it. `None` leaves frames as bare
+    /// `[id][payload]` bytes.
+    pub fn set_framing(&mut self, framing: Option<Framing>) {
+        self.framing = framing;
+        self.cobs = match self.stream.limit() {
+            Some(limit) => CobsDecoder::with_max_len(limit),
+            None => CobsDecoder::new(),
+        };
+    }
+
+    fn layout_of(&self, msg: &dyn CerealBox) -> u8 {
+        match &self.peer {
+            Some(peer) => msg.layout_for_peer(peer),
+            None => msg.layout_version(),
+        }
+    }
+
+    /// Adds a Cereal Box flavor to the Packager.
+    ///
+    /// Every frame with the id of `T` is poured into a fresh
+    /// `T::default()`.
     ///
     /// # Panics
     ///
     /// Panics if a cereal cereal_box has the same id key as a previously added cereal_box.
-    pub fn add_flavor(&mut self, cereal_box: Box<dyn CerealBox>){
-        let id = cereal_box.get_id();
+    pub fn add_flavor<T: CerealBox + Default + 'static>(&mut self) {
+        let id = T::default().get_id();
+        self.add_factory(id, || Box::new(T::default()));
+    }
+
+    /// Adds a [`Factory`] that builds the boxes for frames with `id`.
+    ///
+    /// Use this for flavors without a [`Default`], or that need some
+    /// setup before they are poured into.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a flavor with the same id has already been added.
+    pub fn add_factory<F>(&mut self, id: u8, factory: F)
+    where
+        F: Fn() -> Box<dyn CerealBox> + Send + 'static,
+    {
         if self.map.contains_key(&id) {
             panic!("Error adding cereal_box ot Packager! Multiple flavors have id: {}", id);
         }
-        self.map.insert(id, cereal_box);
+        self.map.insert(id, Box::new(factory));
+    }
+
+    /// Registers a handler that [`Packager::pack`] calls with every box
+    /// of type `T` it packs. The flavor is added too if it has not been
+    /// already.
+    ///
+    /// The handler can capture application state, such as a channel to
+    /// another thread or a counter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///   use std::sync::{Arc, Mutex};
+    ///
+    ///   use open_channel::cereal::{CerealBox, CerealRead, CerealStream, Packager};
+    ///   use open_channel::error::CerealError;
+    ///
+    ///   #[derive(Default)]
+    ///   struct Reading(u8);
+    ///
+    ///   impl CerealBox for Reading {
+    ///       fn get_id(&self) -> u8 {
+    ///           6
+    ///       }
+    ///
+    ///       fn pour_out(&self, package: &mut CerealStream) -> Result<(), CerealError> {
+    ///           package.push_u8(self.0)
+    ///       }
+    ///
+    ///       fn pour_in(&mut self, package: &mut dyn CerealRead) -> Result<(), CerealError> {
+    ///           self.0 = package.try_pop_u8()?;
+    ///           Ok(())
+    ///       }
+    ///   }
+    ///
+    ///   let total = Arc::new(Mutex::new(0));
+    ///   let mut packager = Packager::new();
+    ///   let sum = total.clone();
+    ///   packager.on::<Reading>(move |msg| *sum.lock().unwrap() += msg.0);
+    ///
+    ///   packager.unpack(&Reading(3)).unwrap();
+    ///   packager.unpack(&Reading(4)).unwrap();
+    ///   while !packager.is_empty() {
+    ///       packager.pack().unwrap();
+    ///   }
+    ///   assert_eq!(*total.lock().unwrap(), 7);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if a handler for the same id has already been registered,
+    /// or if a different flavor has already been added with that id.
+    pub fn on<T>(&mut self, mut handler: impl FnMut(&T) + Send + 'static)
+    where
+        T: CerealBox + Default + 'static,
+    {
+        let id = T::default().get_id();
+        if self.handlers.contains_key(&id) {
+            panic!("Error adding handler to Packager! Multiple handlers have id: {}", id);
+        }
+        match self.map.get(&id) {
+            Some(factory) if !factory().is::<T>() => {
+                panic!("Error adding handler to Packager! Another flavor has id: {}", id);
+            }
+            Some(_) => {}
+            None => self.add_flavor::<T>(),
+        }
+        self.handlers.insert(id, Box::new(move |msg: &dyn CerealBox| {
+            if let Some(msg) = msg.downcast_ref::<T>() {
+                handler(msg);
+            }
+        }));
+    }
+
+    /// Feeds raw bytes received from the link into the packager.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Overflow`] if the bytes would take a bounded
+    /// packager past its limit. Nothing is pushed in that case.
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> Result<(), CerealError> {
+        self.stream.push_bytes(bytes)
+    }
+
+    /// Writes every pending byte of the stream to `writer`.
+    ///
+    /// Returns the number of bytes written. Bytes are removed from the
+    /// stream as the writer accepts them, so a nonblocking writer that
+    /// takes part of them before it would block leaves only the rest for
+    /// the next call. Writing stops early, with the count so far, once
+    /// the writer would block or accepts nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///   use open_channel::cereal::Packager;
+    ///
+    ///   let mut sender = Packager::new();
+    ///   sender.push_bytes(&[1, 2, 3]).unwrap();
+    ///
+    ///   let mut wire: Vec<u8> = Vec::new();
+    ///   assert_eq!(sender.write_to(&mut wire).unwrap(), 3);
+    ///   assert!(sender.is_empty());
+    ///
+    ///   let mut receiver = Packager::new();
+    ///   assert_eq!(receiver.read_from(&mut wire.as_slice()).unwrap(), 3);
+    ///   assert!(!receiver.is_empty());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns any error raised by the writer, other than
+    /// [`io::ErrorKind::WouldBlock`] and [`io::ErrorKind::Interrupted`].
+    /// Bytes it accepted before the error are still removed.
+    pub fn write_to<W: io::Write>(&mut self, writer: &mut W) -> io::Result<usize> {
+        let mut num_bytes = 0;
+        while !self.stream.is_empty() {
+            let written = match writer.write(self.stream.get_vec()) {
+                Ok(0) => break,
+                Ok(written) => written,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            };
+            self.stream.advance(written);
+            self.offset += written;
+            num_bytes += written;
+        }
+        Ok(num_bytes)
+    }
+
+    /// Performs a single read from `reader` and feeds the bytes into the
+    /// packager, ready to be packed.
+    ///
+    /// Returns the number of bytes read, which is 0 once the reader has
+    /// reached the end of its data. A bounded packager never reads more
+    /// than it has room for.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error raised by the reader, or an error wrapping
+    /// [`CerealError::Overflow`] if a bounded packager is already full.
+    pub fn read_from<R: io::Read>(&mut self, reader: &mut R) -> io::Result<usize> {
+        let free = self.stream.free_space();
+        if free == 0 {
+            return Err(io::Error::other(CerealError::Overflow { requested: 1, free }));
+        }
+        let mut chunk = [0; READ_CHUNK];
+        let chunk = &mut chunk[..free.min(READ_CHUNK)];
+        let num_bytes = reader.read(chunk)?;
+        self.stream.buf.extend_from_slice(&chunk[..num_bytes]);
+        Ok(num_bytes)
     }
 
     /// unpack a ceral box into a cereal stream.
-    pub fn unpack(&mut self, msg: &dyn CerealBox){
-        let id = msg.get_id();
-        self.stream.push_bytes(&[id]);
-        msg.pour_out(&mut self.stream);
+    ///
+    /// Space for the whole frame is reserved up front from the
+    /// [`CerealBox::encoded_len`] of the box, and the box is only added to
+    /// the stream if all of it fits. The box is laid out for the peer
+    /// version, if one is known, and wrapped in the [`Framing`] of the
+    /// packager, if one is set.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the box can not be poured
+    /// out, or would take a bounded packager past its limit. A box that
+    /// pours out a different number of bytes than its encoded length
+    /// fails with [`CerealError::LengthMismatch`].
+    pub fn unpack(&mut self, msg: &dyn CerealBox) -> Result<(), CerealError> {
+        let package = self.encode(msg)?;
+        match &self.framing {
+            Some(framing) => framing.encode(package.get_vec(), &mut self.stream),
+            None => self.stream.push_bytes(package.get_vec()),
+        }
+    }
+
+    fn encode(&mut self, msg: &dyn CerealBox) -> Result<CerealStream, CerealError> {
+        let layout = self.layout_of(msg);
+        let mut package = CerealStream::with_endian(self.stream.endian());
+        if msg.extensible() {
+            let mut contents = CerealStream::with_endian(self.stream.endian());
+            msg.pour_out_layout(layout, &mut contents)?;
+            package.push_u8(msg.get_id())?;
+            package.push_blob(contents.get_vec(), LengthPrefix::Varint)?;
+            return Ok(package);
+        }
+        if layout != msg.layout_version() {
+            package.push_u8(msg.get_id())?;
+            msg.pour_out_layout(layout, &mut package)?;
+            return Ok(package);
+        }
+
+        let len = msg.encoded_len()?;
+        let overhead = self.framing.map_or(0, |framing| framing.overhead(1 + len));
+        self.stream.reserve(overhead + 1 + len)?;
+        package.reserve(1 + len)?;
+        package.push_u8(msg.get_id())?;
+        msg.pour_out(&mut package)?;
+        if package.len() != 1 + len {
+            return Err(CerealError::LengthMismatch {
+                expected: len,
+                actual: package.len() - 1,
+            });
+        }
+        Ok(package)
     }
 
     /// pack a ceral box from the cereal stream.
     ///
+    /// Each frame is poured into a new box built by the factory of its
+    /// flavor. The box is handed to the handler registered for it with
+    /// [`Packager::on`], if any, and then returned to the caller, who can
+    /// get the message back out with `downcast_ref`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///   use open_channel::cereal::{CerealBox, CerealRead, CerealStream, Packager};
+    ///   use open_channel::error::CerealError;
+    ///
+    ///   #[derive(Default)]
+    ///   struct Channel(u8);
+    ///
+    ///   impl CerealBox for Channel {
+    ///       fn get_id(&self) -> u8 {
+    ///           2
+    ///       }
+    ///
+    ///       fn pour_out(&self, package: &mut CerealStream) -> Result<(), CerealError> {
+    ///           package.push_u8(self.0)
+    ///       }
+    ///
+    ///       fn pour_in(&mut self, package: &mut dyn CerealRead) -> Result<(), CerealError> {
+    ///           self.0 = package.try_pop_u8()?;
+    ///           Ok(())
+    ///       }
+    ///   }
+    ///
+    ///   let mut packager = Packager::new();
+    ///   packager.add_flavor::<Channel>();
+    ///   packager.unpack(&Channel(1)).unwrap();
+    ///   packager.unpack(&Channel(2)).unwrap();
+    ///
+    ///   let first = packager.pack().unwrap();
+    ///   let second = packager.pack().unwrap();
+    ///   assert_eq!(first.downcast_ref::<Channel>().unwrap().0, 1);
+    ///   assert_eq!(second.downcast_ref::<Channel>().unwrap().0, 2);
+    ///
+    ///   packager.push_bytes(&[7]).unwrap();
+    ///
+    ///   let err = packager.pack().unwrap_err();
+    ///   assert_eq!(err.root(), &CerealError::UnknownId { id: 7 });
+    ///   assert!(packager.is_empty());
+    /// ```
+    ///
     /// # Errors
     ///
+    /// Errors raised by a box are wrapped in [`CerealError::InBox`].
+    /// A box with a [`CerealBox::fixed_len`] that uses a different number
+    /// of bytes fails with [`CerealError::LengthMismatch`].
+    ///
     /// This function will return an error if the cereal stream does not
-    /// have enough bytes in it.
-    pub fn pack(&mut self) -> Result<(), String> {
-        let id = self.stream.pop_byte();
-        let mut result: Result<(), String> = Ok(());
-        self.map.entry(id).and_modify(|msg| {
-            result = msg.pour_in(&mut self.stream);
-        });
+    /// have enough bytes in it. The stream is then left untouched so the
+    /// pack can be retried once more bytes have been pushed.
+    ///
+    /// A frame whose id no flavor uses fails with
+    /// [`CerealError::UnknownId`]. Its id byte is dropped so the next pack
+    /// moves on. A box that fails to pour in for any other reason drops
+    /// its id byte and every byte it read before failing.
+    ///
+    /// With a [`Framing`] set, a frame that fails to unwrap is reported
+    /// with the error of [`Framing::decode`] and skipped over. A box that
+    /// fails to pour in is then dropped along with its whole frame, and
+    /// one that leaves bytes of its frame unread fails with
+    /// [`CerealError::TrailingBytes`].
+    pub fn pack(&mut self) -> Result<Box<dyn CerealBox>, CerealError> {
+        let start = self.stream.len();
+        self.stream.mark();
+        let result = self.pack_marked();
+        match &result {
+            Ok(_) => self.stream.commit(),
+            Err(err) => match err.root() {
+                // the COBS decoder has already taken in the start of the
+                // frame, so its bytes must not be fed to it again
+                CerealError::Underflow { .. } if matches!(self.framing, Some(Framing::Cobs)) => {
+                    self.stream.commit()
+                }
+                CerealError::Underflow { .. } => self.stream.rollback(),
+                _ => self.stream.commit(),
+            },
+        }
+        self.offset += start - self.stream.len();
         result
     }
 
+    /// Packs every complete frame in the stream, one per iteration.
+    ///
+    /// The iterator ends once the stream is empty or only holds the start
+    /// of a frame, which stays in the stream until the rest arrives. Any
+    /// other error is yielded, and the iterator carries on after the bytes
+    /// [`Packager::pack`] dropped for it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///   use open_channel::cereal::{CerealBox, CerealRead, CerealStream, Packager};
+    ///   use open_channel::error::CerealError;
+    ///
+    ///   #[derive(Default)]
+    ///   struct Channel(u8);
+    ///
+    ///   impl CerealBox for Channel {
+    ///       fn get_id(&self) -> u8 {
+    ///           2
+    ///       }
+    ///
+    ///       fn pour_out(&self, package: &mut CerealStream) -> Result<(), CerealError> {
+    ///           package.push_u8(self.0)
+    ///       }
+    ///
+    ///       fn pour_in(&mut self, package: &mut dyn CerealRead) -> Result<(), CerealError> {
+    ///           self.0 = package.try_pop_u8()?;
+    ///           Ok(())
+    ///       }
+    ///   }
+    ///
+    ///   let mut packager = Packager::new();
+    ///   packager.add_flavor::<Channel>();
+    ///   packager.push_bytes(&[2, 5, 2, 6, 2]).unwrap();
+    ///
+    ///   let mut channels = Vec::new();
+    ///   for msg in packager.drain_messages() {
+    ///       if let Some(channel) = msg.unwrap().downcast_ref::<Channel>() {
+    ///           channels.push(channel.0);
+    ///       }
+    ///   }
+    ///   assert_eq!(channels, [5, 6]);
+    ///   assert!(!packager.is_empty());
+    /// ```
+    pub fn drain_messages(&mut self) -> DrainMessages<'_> {
+        DrainMessages {
+            packager: self,
+            done: false,
+        }
+    }
+
+    fn pack_marked(&mut self) -> Result<Box<dyn CerealBox>, CerealError> {
+        let msg = match self.framing {
+            Some(framing) => self.decode_framed(framing)?,
+            None => self.decode_marked()?,
+        };
+        if let Some(peer) = msg.peer_version() {
+            self.peer = Some(peer);
+        }
+        if let Some(handler) = self.handlers.get_mut(&msg.get_id()) {
+            handler(msg.as_ref());
+        }
+        Ok(msg)
+    }
+
+    fn decode_marked(&mut self) -> Result<Box<dyn CerealBox>, CerealError> {
+        let offset = self.offset;
+        let id = self.stream.try_pop_byte()?;
+        let mut msg = self.build(id).map_err(|err| err.in_box(id, offset))?;
+        let layout = self.layout_of(msg.as_ref());
+        // a box longer than a bounded stream can hold would never arrive
+        let max_len = self.stream.limit().unwrap_or(usize::MAX);
+        Self::pour_in_checked(msg.as_mut(), layout, max_len, &mut self.stream)
+            .map(|()| msg)
+            .map_err(|err| err.in_box(id, offset))
+    }
+
+    fn decode_framed(&mut self, framing: Framing) -> Result<Box<dyn CerealBox>, CerealError> {
+        let (offset, frame) = match framing {
+            Framing::Cobs => {
+                let frame = self.next_cobs_frame();
+                (self.cobs_start, frame?)
+            }
+            framing => (self.offset, framing.decode(&mut self.stream)?),
+        };
+        let len = frame.len();
+        let mut package = CerealReader::with_endian(&frame, self.stream.endian());
+        let id = package.try_pop_byte().map_err(|_| CerealError::FrameTooShort { len })?;
+        let mut msg = self.build(id).map_err(|err| err.in_box(id, offset))?;
+        let layout = self.layout_of(msg.as_ref());
+        Self::pour_in_checked(msg.as_mut(), layout, usize::MAX, &mut package)
+            .and_then(|()| match package.len() {
+                0 => Ok(()),
+                count => Err(CerealError::TrailingBytes { count }),
+            })
+            .map_err(|err| match err {
+                CerealError::Underflow { .. } => CerealError::FrameTooShort { len },
+                err => err,
+            })
+            .map(|()| msg)
+            .map_err(|err| err.in_box(id, offset))
+    }
+
+    /// Feeds the stream into the COBS decoder up to the end of the next
+    /// frame.
+    fn next_cobs_frame(&mut self) -> Result<Vec<u8>, CerealError> {
+        let mut used = 0;
+        let mut frame = None;
+        for &byte in self.stream.remaining() {
+            if !self.cobs.in_frame() {
+                self.cobs_start = self.offset + used;
+            }
+            used += 1;
+            frame = self.cobs.push(byte);
+            if frame.is_some() {
+                break;
+            }
+        }
+        self.stream.skip(used)?;
+        frame.unwrap_or(Err(CerealError::Underflow { requested: 1, available: 0 }))
+    }
+
+    /// Builds an empty box for the flavor with `id`.
+    fn build(&self, id: u8) -> Result<Box<dyn CerealBox>, CerealError> {
+        match self.map.get(&id) {
+            Some(factory) => Ok(factory()),
+            None => Err(CerealError::UnknownId { id }),
+        }
+    }
+
+    /// Pours `stream` into `msg`, refusing an extensible box whose length
+    /// is over `max_len`.
+    fn pour_in_checked(
+        msg: &mut dyn CerealBox,
+        layout: u8,
+        max_len: usize,
+        stream: &mut dyn CerealRead,
+    ) -> Result<(), CerealError> {
+        if msg.extensible() {
+            let len = stream.try_pop_len(LengthPrefix::Varint, max_len)?;
+            let frame = stream.try_pop_bytes(len)?;
+            let mut reader = CerealReader::with_endian(&frame, stream.endian());
+            return msg.pour_in_layout(layout, &mut reader).map_err(|err| match err {
+                CerealError::Underflow { .. } => CerealError::FrameTooShort { len },
+                err => err,
+            });
+        }
+
+        let start = stream.len();
+        if layout != msg.layout_version() {
+            msg.pour_in_layout(layout, stream)?;
+            let used = start - stream.len();
+            let mut scratch = CerealStream::with_endian(stream.endian());
+            msg.pour_out_layout(layout, &mut scratch)?;
+            if used != scratch.len() {
+                return Err(CerealError::LengthMismatch { expected: scratch.len(), actual: used });
+            }
+            return Ok(());
+        }
+
+        let fixed_len = msg.fixed_len();
+        if let Some(len) = fixed_len {
+            if stream.len() < len {
+                return Err(CerealError::Underflow { requested: len, available: stream.len() });
+            }
+        }
+        msg.pour_in(stream)?;
+        // a box without a fixed length would have to be poured out again
+        // to learn its length, so only fixed ones are checked
+        let used = start - stream.len();
+        match fixed_len {
+            Some(len) if used != len => Err(CerealError::LengthMismatch { expected: len, actual: used }),
+            _ => Ok(()),
+        }
+    }
+
+}
+
+/// An iterator over the boxes packed from a [`Packager`].
+///
+/// Created by [`Packager::drain_messages`].
+pub struct DrainMessages<'a> {
+    packager: &'a mut Packager,
+    done: bool,
+}
+
+impl Iterator for DrainMessages<'_> {
+    type Item = Result<Box<dyn CerealBox>, CerealError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let offset = self.packager.offset;
+        match self.packager.pack() {
+            Ok(msg) => Some(Ok(msg)),
+            Err(err) => match err.root() {
+                CerealError::Underflow { .. } => {
+                    self.done = true;
+                    None
+                }
+                _ => {
+                    // an error that left its bytes in the stream would
+                    // come straight back on the next pack
+                    self.done = self.packager.offset == offset;
+                    Some(Err(err))
+                }
+            },
+        }
+    }
+}
+
+impl Default for Packager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
@@ -0,0 +1,255 @@
+use std::fmt;
+
+/// A checksum that [`Framing::Sync`](super::frame::Framing::Sync) puts
+/// in the trailer of each frame.
+///
+/// The checksum is pushed in the byte order of the stream, as an
+/// unsigned integer [`Checksum::width`] bytes wide.
+///
+/// # Examples
+///
+/// ```
+///   use open_channel::checksum::{Checksum, CRC16_MODBUS, CRC32_MPEG2};
+///
+///   assert_eq!(CRC16_MODBUS.checksum(b"123456789"), 0x4B37);
+///   assert_eq!(CRC16_MODBUS.width(), 2);
+///   assert_eq!(CRC32_MPEG2.checksum(b"123456789"), 0x0376_E6E7);
+///   assert_eq!(CRC32_MPEG2.width(), 4);
+/// ```
+pub trait Checksum: fmt::Debug + Sync {
+    /// Returns the number of bytes the checksum takes in a trailer,
+    /// which is 1, 2 or 4.
+    fn width(&self) -> usize;
+
+    /// Returns the checksum of `bytes`.
+    fn checksum(&self, bytes: &[u8]) -> u32;
+}
+
+/// The Fletcher-16 checksum, two running sums modulo 255.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Fletcher16;
+
+impl Checksum for Fletcher16 {
+    fn width(&self) -> usize {
+        2
+    }
+
+    fn checksum(&self, bytes: &[u8]) -> u32 {
+        let (mut sum1, mut sum2) = (0u16, 0u16);
+        for &byte in bytes {
+            sum1 = (sum1 + byte as u16) % 255;
+            sum2 = (sum2 + sum1) % 255;
+        }
+        ((sum2 << 8) | sum1) as u32
+    }
+}
+
+/// A table driven 16 bit CRC.
+#[derive(Debug, Clone)]
+pub struct Crc16 {
+    table: [u16; 256],
+    init: u16,
+    reflect: bool,
+    xor_out: u16,
+}
+
+impl Crc16 {
+    /// Creates a new [`Crc16`] from its polynomial, in normal (not
+    /// reversed) form, its initial value, whether bytes are fed in least
+    /// significant bit first, and the value the result is xored with.
+    pub const fn new(poly: u16, init: u16, reflect: bool, xor_out: u16) -> Self {
+        let mut table = [0u16; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = if reflect { i as u16 } else { (i as u16) << 8 };
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if reflect {
+                    if crc & 1 != 0 { (crc >> 1) ^ poly.reverse_bits() } else { crc >> 1 }
+                } else if crc & 0x8000 != 0 {
+                    (crc << 1) ^ poly
+                } else {
+                    crc << 1
+                };
+                bit += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        Self { table, init, reflect, xor_out }
+    }
+}
+
+impl Checksum for Crc16 {
+    fn width(&self) -> usize {
+        2
+    }
+
+    fn checksum(&self, bytes: &[u8]) -> u32 {
+        let mut crc = self.init;
+        for &byte in bytes {
+            crc = if self.reflect {
+                (crc >> 8) ^ self.table[((crc ^ byte as u16) & 0xFF) as usize]
+            } else {
+                (crc << 8) ^ self.table[((crc >> 8) ^ byte as u16) as usize]
+            };
+        }
+        (crc ^ self.xor_out) as u32
+    }
+}
+
+/// A table driven 32 bit CRC.
+#[derive(Debug, Clone)]
+pub struct Crc32 {
+    table: [u32; 256],
+    init: u32,
+    reflect: bool,
+    xor_out: u32,
+}
+
+impl Crc32 {
+    /// Creates a new [`Crc32`] from its polynomial, in normal (not
+    /// reversed) form, its initial value, whether bytes are fed in least
+    /// significant bit first, and the value the result is xored with.
+    pub const fn new(poly: u32, init: u32, reflect: bool, xor_out: u32) -> Self {
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = if reflect { i as u32 } else { (i as u32) << 24 };
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if reflect {
+                    if crc & 1 != 0 { (crc >> 1) ^ poly.reverse_bits() } else { crc >> 1 }
+                } else if crc & 0x8000_0000 != 0 {
+                    (crc << 1) ^ poly
+                } else {
+                    crc << 1
+                };
+                bit += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        Self { table, init, reflect, xor_out }
+    }
+
+    fn update(&self, mut crc: u32, bytes: &[u8]) -> u32 {
+        for &byte in bytes {
+            crc = if self.reflect {
+                (crc >> 8) ^ self.table[((crc ^ byte as u32) & 0xFF) as usize]
+            } else {
+                (crc << 8) ^ self.table[((crc >> 24) ^ byte as u32) as usize]
+            };
+        }
+        crc
+    }
+}
+
+impl Checksum for Crc32 {
+    fn width(&self) -> usize {
+        4
+    }
+
+    fn checksum(&self, bytes: &[u8]) -> u32 {
+        self.update(self.init, bytes) ^ self.xor_out
+    }
+}
+
+/// The CRC-32 computed by the hardware CRC unit of the STM32F4 family.
+///
+/// The unit computes CRC-32/MPEG-2 over the 32 bit words written to its
+/// data register, most significant bit first. The firmware writes the
+/// bytes of a frame as little endian words, so each group of four bytes
+/// is fed in reverse order. A final partial word is padded with zero
+/// bytes.
+///
+/// # Examples
+///
+/// ```
+///   use open_channel::checksum::{Checksum, CRC32_STM32};
+///
+///   // CRC->DR = 0x12345678
+///   assert_eq!(CRC32_STM32.checksum(&0x1234_5678u32.to_le_bytes()), 0xDF8A_8A2B);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Stm32Crc {
+    crc: Crc32,
+}
+
+impl Stm32Crc {
+    /// Creates a new [`Stm32Crc`].
+    pub const fn new() -> Self {
+        Self { crc: CRC32_MPEG2 }
+    }
+}
+
+impl Default for Stm32Crc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Checksum for Stm32Crc {
+    fn width(&self) -> usize {
+        4
+    }
+
+    fn checksum(&self, bytes: &[u8]) -> u32 {
+        let mut crc = self.crc.init;
+        for chunk in bytes.chunks(4) {
+            let mut word = [0u8; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            crc = self.crc.update(crc, &u32::from_le_bytes(word).to_be_bytes());
+        }
+        crc ^ self.crc.xor_out
+    }
+}
+
+/// CRC-16/CCITT-FALSE: polynomial 0x1021, initial value 0xFFFF.
+pub const CRC16_CCITT: Crc16 = Crc16::new(0x1021, 0xFFFF, false, 0);
+
+/// CRC-16/MODBUS: polynomial 0x8005 fed least significant bit first,
+/// initial value 0xFFFF.
+pub const CRC16_MODBUS: Crc16 = Crc16::new(0x8005, 0xFFFF, true, 0);
+
+/// CRC-32/ISO-HDLC, the CRC-32 of zlib and Ethernet.
+pub const CRC32: Crc32 = Crc32::new(0x04C1_1DB7, 0xFFFF_FFFF, true, 0xFFFF_FFFF);
+
+/// CRC-32/MPEG-2: polynomial 0x04C11DB7 fed most significant bit
+/// first, initial value 0xFFFFFFFF and no final xor.
+pub const CRC32_MPEG2: Crc32 = Crc32::new(0x04C1_1DB7, 0xFFFF_FFFF, false, 0);
+
+/// The CRC-32 of the STM32 hardware CRC unit, see [`Stm32Crc`].
+pub const CRC32_STM32: Stm32Crc = Stm32Crc::new();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHECK: &[u8] = b"123456789";
+
+    #[test]
+    fn check_values() {
+        assert_eq!(Fletcher16.checksum(b"abcde"), 0xC8F0);
+        assert_eq!(CRC16_CCITT.checksum(CHECK), 0x29B1);
+        assert_eq!(CRC16_MODBUS.checksum(CHECK), 0x4B37);
+        assert_eq!(CRC32.checksum(CHECK), 0xCBF4_3926);
+        assert_eq!(CRC32_MPEG2.checksum(CHECK), 0x0376_E6E7);
+        assert_eq!(CRC16_CCITT.checksum(&[]), 0xFFFF);
+        assert_eq!(CRC32.checksum(&[]), 0);
+    }
+
+    #[test]
+    fn stm32_words() {
+        // whole words match MPEG-2 over the bytes of each little endian
+        // word taken most significant first
+        let words = [0x1234_5678u32, 0xDEAD_BEEF];
+        let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        let swapped: Vec<u8> = words.iter().flat_map(|w| w.to_be_bytes()).collect();
+        assert_eq!(CRC32_STM32.checksum(&bytes), CRC32_MPEG2.checksum(&swapped));
+        assert_eq!(CRC32_STM32.checksum(&0u32.to_le_bytes()), 0xC704_DD7B);
+
+        // a partial word is padded with zero bytes
+        assert_eq!(CRC32_STM32.checksum(&[0x78, 0x56]), CRC32_STM32.checksum(&[0x78, 0x56, 0, 0]));
+    }
+}
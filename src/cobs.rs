@@ -0,0 +1,292 @@
+use super::error::CerealError;
+
+/// The byte that ends every COBS frame, and that never shows up inside
+/// one.
+pub const DELIMITER: u8 = 0x00;
+
+/// The longest run of bytes a single COBS code byte can cover.
+const MAX_CODE: u8 = 0xFF;
+
+/// Returns the most bytes [`encode`] can add to `len` bytes of data, not
+/// counting the delimiter.
+pub fn max_overhead(len: usize) -> usize {
+    len / (MAX_CODE as usize - 1) + 1
+}
+
+/// Encodes `data` with Consistent Overhead Byte Stuffing, so that it no
+/// longer holds any [`DELIMITER`] bytes. The delimiter itself is not
+/// added.
+///
+/// # Examples
+///
+/// ```
+///   use open_channel::cobs;
+///
+///   let encoded = cobs::encode(&[0x11, 0x22, 0x00, 0x33]);
+///   assert_eq!(encoded, [0x03, 0x11, 0x22, 0x02, 0x33]);
+///   assert_eq!(cobs::decode(&encoded).unwrap(), [0x11, 0x22, 0x00, 0x33]);
+/// ```
+pub fn encode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + max_overhead(data.len()));
+    let mut code_at = 0;
+    let mut code = 1;
+    out.push(0);
+    for &byte in data {
+        if code == MAX_CODE {
+            out[code_at] = code;
+            code_at = out.len();
+            code = 1;
+            out.push(0);
+        }
+        if byte == DELIMITER {
+            out[code_at] = code;
+            code_at = out.len();
+            code = 1;
+            out.push(0);
+        } else {
+            out.push(byte);
+            code += 1;
+        }
+    }
+    out[code_at] = code;
+    out
+}
+
+/// Decodes one COBS encoded frame, without its delimiter.
+///
+/// # Errors
+///
+/// Returns [`CerealError::InvalidFormat`] if the frame holds a
+/// [`DELIMITER`] or ends part way through a run.
+pub fn decode(encoded: &[u8]) -> Result<Vec<u8>, CerealError> {
+    if encoded.contains(&DELIMITER) {
+        return Err(CerealError::InvalidFormat(String::from(
+            "COBS frame holds a delimiter",
+        )));
+    }
+    let mut decoder = CobsDecoder::new();
+    encoded.iter().for_each(|&byte| {
+        decoder.push(byte);
+    });
+    decoder.push(DELIMITER).unwrap_or(Ok(Vec::new()))
+}
+
+/// Decodes COBS frames from bytes that arrive in chunks of any size.
+///
+/// # Examples
+///
+/// ```
+///   use open_channel::cobs::CobsDecoder;
+///
+///   let mut decoder = CobsDecoder::new();
+///   assert_eq!(decoder.feed(&[0x03, 0x11]).count(), 0);
+///
+///   let frames: Vec<_> = decoder.feed(&[0x22, 0x02, 0x33, 0x00, 0x02]).collect();
+///   assert_eq!(frames, [Ok(vec![0x11, 0x22, 0x00, 0x33])]);
+///
+///   let frames: Vec<_> = decoder.feed(&[0x44, 0x00]).collect();
+///   assert_eq!(frames, [Ok(vec![0x44])]);
+/// ```
+#[derive(Debug, Default)]
+pub struct CobsDecoder {
+    frame: Vec<u8>,
+    // bytes left in the current run, 0 when the next byte is a code
+    remaining: u8,
+    // whether a zero goes between the current run and the next one
+    zero_after: bool,
+    started: bool,
+    max_len: Option<usize>,
+    // set once the current frame has grown past `max_len`, so the rest
+    // of it is dropped up to its delimiter
+    overflowed: bool,
+}
+
+impl CobsDecoder {
+    /// Creates a new [`CobsDecoder`], waiting for the start of a frame.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new [`CobsDecoder`] that refuses frames that decode to
+    /// more than `max_len` bytes, rather than holding on to them until
+    /// their delimiter arrives.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///   use open_channel::cobs::CobsDecoder;
+    ///   use open_channel::error::CerealError;
+    ///
+    ///   let mut decoder = CobsDecoder::with_max_len(2);
+    ///   let frames: Vec<_> = decoder.feed(&[0x05, 0x11, 0x22, 0x33, 0x44, 0x00, 0x02, 0x55, 0x00]).collect();
+    ///   assert_eq!(frames, [Err(CerealError::LengthOverflow { len: 3, max: 2 }), Ok(vec![0x55])]);
+    /// ```
+    pub fn with_max_len(max_len: usize) -> Self {
+        Self { max_len: Some(max_len), ..Self::default() }
+    }
+
+    /// Returns true if part of a frame has been pushed but not its
+    /// delimiter.
+    pub fn in_frame(&self) -> bool {
+        self.started
+    }
+
+    /// Pushes one byte into the decoder. Returns the decoded frame once
+    /// its delimiter arrives. Delimiters with no frame before them are
+    /// skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::InvalidFormat`] for a frame that ends part
+    /// way through a run. The decoder then waits for the next frame.
+    ///
+    /// Returns [`CerealError::LengthOverflow`] as soon as a frame grows
+    /// past the `max_len` of the decoder. The rest of that frame is
+    /// dropped, and the decoder waits for the frame after its delimiter.
+    pub fn push(&mut self, byte: u8) -> Option<Result<Vec<u8>, CerealError>> {
+        if byte == DELIMITER {
+            if !self.started {
+                return None;
+            }
+            let truncated = self.remaining != 0;
+            let overflowed = self.overflowed;
+            let frame = std::mem::take(&mut self.frame);
+            self.reset();
+            if overflowed {
+                return None;
+            }
+            if truncated {
+                return Some(Err(CerealError::InvalidFormat(String::from(
+                    "COBS frame ended part way through a run",
+                ))));
+            }
+            return Some(Ok(frame));
+        }
+
+        self.started = true;
+        if self.overflowed {
+            return None;
+        }
+        if self.remaining == 0 {
+            if self.zero_after {
+                self.frame.push(0);
+            }
+            self.remaining = byte - 1;
+            self.zero_after = byte != MAX_CODE;
+        } else {
+            self.frame.push(byte);
+            self.remaining -= 1;
+        }
+        match self.max_len {
+            Some(max) if self.frame.len() > max => {
+                let len = self.frame.len();
+                self.frame = Vec::new();
+                self.overflowed = true;
+                Some(Err(CerealError::LengthOverflow { len, max }))
+            }
+            _ => None,
+        }
+    }
+
+    /// Waits for the next frame, keeping the `max_len`.
+    fn reset(&mut self) {
+        *self = Self { max_len: self.max_len, ..Self::default() };
+    }
+
+    /// Pushes a chunk of bytes into the decoder, returning an iterator
+    /// over the frames completed by it.
+    pub fn feed<'a>(&'a mut self, bytes: &'a [u8]) -> impl Iterator<Item = Result<Vec<u8>, CerealError>> + 'a {
+        bytes.iter().filter_map(move |&byte| self.push(byte))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(data: &[u8], encoded: &[u8]) {
+        assert_eq!(encode(data), encoded);
+        assert_eq!(decode(encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn known_frames() {
+        check(&[], &[0x01]);
+        check(&[0x00], &[0x01, 0x01]);
+        check(&[0x00, 0x00], &[0x01, 0x01, 0x01]);
+        check(&[0x00, 0x11, 0x00], &[0x01, 0x02, 0x11, 0x01]);
+        check(&[0x11, 0x22, 0x00, 0x33], &[0x03, 0x11, 0x22, 0x02, 0x33]);
+        check(&[0x11, 0x22, 0x33, 0x44], &[0x05, 0x11, 0x22, 0x33, 0x44]);
+        check(&[0x11, 0x00, 0x00, 0x00], &[0x02, 0x11, 0x01, 0x01, 0x01]);
+
+        let run: Vec<u8> = (0x01..=0xFE).collect();
+        check(&run, &[&[0xFF][..], &run].concat());
+
+        let run: Vec<u8> = (0x00..=0xFE).collect();
+        check(&run, &[&[0x01, 0xFF][..], &run[1..]].concat());
+
+        let run: Vec<u8> = (0x01..=0xFF).collect();
+        check(&run, &[&[0xFF][..], &run[..254], &[0x02, 0xFF]].concat());
+
+        let run: Vec<u8> = (0x02..=0xFF).chain([0x00]).collect();
+        check(&run, &[&[0xFF][..], &run[..254], &[0x01, 0x01]].concat());
+
+        let run: Vec<u8> = (0x03..=0xFF).chain([0x00, 0x01]).collect();
+        check(&run, &[&[0xFE][..], &run[..253], &[0x02, 0x01]].concat());
+    }
+
+    #[test]
+    fn overhead_is_bounded() {
+        for len in [0, 1, 253, 254, 255, 1000] {
+            let data = vec![0x55; len];
+            assert!(encode(&data).len() <= len + max_overhead(len));
+        }
+    }
+
+    #[test]
+    fn decode_in_chunks() {
+        let frames: Vec<Vec<u8>> = vec![vec![1, 0, 2], vec![0; 3], (0..=255).collect()];
+        let mut wire = vec![DELIMITER];
+        for frame in &frames {
+            wire.extend(encode(frame));
+            wire.push(DELIMITER);
+        }
+
+        for chunk_len in [1, 2, 7, wire.len()] {
+            let mut decoder = CobsDecoder::new();
+            let decoded: Vec<_> = wire
+                .chunks(chunk_len)
+                .flat_map(|chunk| decoder.feed(chunk).collect::<Vec<_>>())
+                .collect::<Result<_, _>>()
+                .unwrap();
+            assert_eq!(decoded, frames);
+            assert!(!decoder.in_frame());
+        }
+    }
+
+    #[test]
+    fn broken_frames() {
+        let mut decoder = CobsDecoder::new();
+        let results: Vec<_> = decoder.feed(&[0x05, 0x11, 0x00, 0x02, 0x22, 0x00]).collect();
+        assert!(matches!(results[0], Err(CerealError::InvalidFormat(_))));
+        assert_eq!(results[1], Ok(vec![0x22]));
+
+        assert!(decode(&[0x03, 0x00, 0x01]).is_err());
+    }
+
+    #[test]
+    fn frames_over_max_len() {
+        let mut decoder = CobsDecoder::with_max_len(4);
+        let mut wire = vec![0x11; 1000];
+        wire.push(DELIMITER);
+        wire.extend(encode(&[1, 0, 2, 3]));
+        wire.push(DELIMITER);
+
+        let results: Vec<_> = decoder.feed(&wire).collect();
+        assert_eq!(
+            results,
+            [Err(CerealError::LengthOverflow { len: 5, max: 4 }), Ok(vec![1, 0, 2, 3])]
+        );
+        assert!(decoder.frame.capacity() <= 8);
+    }
+}
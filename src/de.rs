@@ -0,0 +1,493 @@
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+
+use super::cereal::{CerealRead, CerealReader, LengthPrefix};
+use super::error::CerealError;
+
+/// Pours any [`DeserializeOwned`] value out of a cereal source, reading
+/// the layout [`ser::to_stream`](super::ser::to_stream) writes.
+///
+/// # Examples
+///
+/// ```
+///   use open_channel::cereal::CerealStream;
+///   use open_channel::de;
+///   use serde_derive::Deserialize;
+///
+///   #[derive(Deserialize, PartialEq, Debug)]
+///   struct AdcData {
+///       channel: u8,
+///       data: Vec<i16>,
+///   }
+///
+///   let mut stream = CerealStream::new();
+///   stream.push_bytes(&[1, 4, 0, 0, 4, 224, 252]).unwrap();
+///   let adc: AdcData = de::from_reader(&mut stream).unwrap();
+///   assert_eq!(adc, AdcData { channel: 1, data: vec![1024, -800] });
+///   assert!(stream.is_empty());
+/// ```
+///
+/// # Errors
+///
+/// This function will return an error if the source lacks sufficient
+/// bytes, or they do not describe a valid value.
+pub fn from_reader<T: DeserializeOwned>(input: &mut dyn CerealRead) -> Result<T, CerealError> {
+    T::deserialize(&mut Deserializer::new(input))
+}
+
+/// Pours any [`DeserializeOwned`] value out of `bytes`, using the default
+/// byte order.
+///
+/// # Errors
+///
+/// This function will return an error if the bytes do not describe a
+/// valid value, or [`CerealError::TrailingBytes`] if they are not all
+/// used.
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CerealError> {
+    let mut reader = CerealReader::new(bytes);
+    let value = from_reader(&mut reader)?;
+    if !reader.is_empty() {
+        return Err(CerealError::TrailingBytes { count: reader.len() });
+    }
+    Ok(value)
+}
+
+/// A serde [`Deserializer`](de::Deserializer) that reads the cereal wire
+/// format from any [`CerealRead`] source.
+///
+/// The format does not describe itself, so only types that know their own
+/// layout, such as derived structs, can be read.
+pub struct Deserializer<'a> {
+    input: &'a mut dyn CerealRead,
+}
+
+impl<'a> Deserializer<'a> {
+    /// Creates a new [`Deserializer`] that pops from `input`.
+    pub fn new(input: &'a mut dyn CerealRead) -> Self {
+        Self { input }
+    }
+
+    /// Reads a `u16` length in bytes and returns how many bytes will be
+    /// left in the source once that many have been read.
+    fn prefixed_end(&mut self) -> Result<usize, CerealError> {
+        let len = self.input.try_pop_len(LengthPrefix::U16, u16::MAX as usize)?;
+        self.input.peek_bytes(len)?;
+        Ok(self.input.len() - len)
+    }
+
+    /// Checks that the source is down to exactly the `end` returned by
+    /// [`Deserializer::prefixed_end`].
+    fn check_prefixed_end(&self, end: usize) -> Result<(), CerealError> {
+        match self.input.len().checked_sub(end) {
+            Some(0) => Ok(()),
+            Some(count) => Err(CerealError::TrailingBytes { count }),
+            None => Err(overrun()),
+        }
+    }
+}
+
+impl de::Error for CerealError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        CerealError::InvalidFormat(msg.to_string())
+    }
+}
+
+fn overrun() -> CerealError {
+    CerealError::InvalidFormat(String::from("element runs past the end of its sequence"))
+}
+
+fn not_self_describing() -> CerealError {
+    CerealError::InvalidFormat(String::from("the cereal format is not self-describing"))
+}
+
+/// Forwards the serde methods for numbers to the typed pop methods.
+macro_rules! deserialize_numbers {
+    ($($method:ident: $pop:ident => $visit:ident;)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CerealError> {
+                visitor.$visit(self.input.$pop()?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'_> {
+    type Error = CerealError;
+
+    deserialize_numbers! {
+        deserialize_bool: try_pop_bool => visit_bool;
+        deserialize_i8: try_pop_i8 => visit_i8;
+        deserialize_i16: try_pop_i16 => visit_i16;
+        deserialize_i32: try_pop_i32 => visit_i32;
+        deserialize_i64: try_pop_i64 => visit_i64;
+        deserialize_u8: try_pop_u8 => visit_u8;
+        deserialize_u16: try_pop_u16 => visit_u16;
+        deserialize_u32: try_pop_u32 => visit_u32;
+        deserialize_u64: try_pop_u64 => visit_u64;
+        deserialize_f32: try_pop_f32 => visit_f32;
+        deserialize_f64: try_pop_f64 => visit_f64;
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, CerealError> {
+        Err(not_self_describing())
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CerealError> {
+        let code = self.input.try_pop_u32()?;
+        let c = char::from_u32(code)
+            .ok_or_else(|| CerealError::InvalidFormat(format!("invalid char {:#x}", code)))?;
+        visitor.visit_char(c)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CerealError> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CerealError> {
+        visitor.visit_string(self.input.try_pop_string(LengthPrefix::U16, u16::MAX as usize)?)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CerealError> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CerealError> {
+        visitor.visit_byte_buf(self.input.try_pop_blob(LengthPrefix::U16, u16::MAX as usize)?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CerealError> {
+        match self.input.try_pop_u8()? {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            value => Err(CerealError::InvalidEnumValue { name: "Option", value }),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CerealError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, CerealError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, CerealError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CerealError> {
+        let end = self.prefixed_end()?;
+        let value = visitor.visit_seq(Prefixed { de: &mut *self, end })?;
+        self.check_prefixed_end(end)?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, CerealError> {
+        visitor.visit_seq(Fields { de: self, left: len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, CerealError> {
+        visitor.visit_seq(Fields { de: self, left: len })
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, CerealError> {
+        let end = self.prefixed_end()?;
+        let value = visitor.visit_map(Prefixed { de: &mut *self, end })?;
+        self.check_prefixed_end(end)?;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, CerealError> {
+        visitor.visit_seq(Fields { de: self, left: fields.len() })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, CerealError> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, CerealError> {
+        Err(not_self_describing())
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, CerealError> {
+        Err(not_self_describing())
+    }
+}
+
+/// Reads a known number of fields one after another.
+struct Fields<'a, 'b> {
+    de: &'b mut Deserializer<'a>,
+    left: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for Fields<'_, '_> {
+    type Error = CerealError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, CerealError> {
+        if self.left == 0 {
+            return Ok(None);
+        }
+        self.left -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left)
+    }
+}
+
+/// Reads elements until the source is down to `end` bytes.
+struct Prefixed<'a, 'b> {
+    de: &'b mut Deserializer<'a>,
+    end: usize,
+}
+
+impl Prefixed<'_, '_> {
+    fn has_more(&self) -> Result<bool, CerealError> {
+        let left = self.de.input.len();
+        if left < self.end {
+            return Err(overrun());
+        }
+        Ok(left > self.end)
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for Prefixed<'_, '_> {
+    type Error = CerealError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, CerealError> {
+        if !self.has_more()? {
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Prefixed<'_, '_> {
+    type Error = CerealError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, CerealError> {
+        if !self.has_more()? {
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, CerealError> {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for &mut Deserializer<'_> {
+    type Error = CerealError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), CerealError> {
+        let index = self.input.try_pop_u8()? as u32;
+        let value = seed.deserialize(IntoDeserializer::<CerealError>::into_deserializer(index))?;
+        Ok((value, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut Deserializer<'_> {
+    type Error = CerealError;
+
+    fn unit_variant(self) -> Result<(), CerealError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, CerealError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, CerealError> {
+        visitor.visit_seq(Fields { de: self, left: len })
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, CerealError> {
+        visitor.visit_seq(Fields { de: self, left: fields.len() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_derive::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::cereal::{CerealStream, Endian};
+    use crate::ser;
+    use crate::serial_params::{CharLength, Parity, StopBits};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct SerialParams {
+        channel: u8,
+        baud: u32,
+        char_len: CharLength,
+        parity: Parity,
+        stop: StopBits,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Reply {
+        Ack,
+        Error(u16),
+        Samples { channel: u8, data: Vec<i16> },
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Everything {
+        flag: bool,
+        letter: char,
+        name: String,
+        scale: Option<f32>,
+        missing: Option<u64>,
+        pair: (i8, u16),
+        nested: Vec<Vec<u8>>,
+        table: BTreeMap<u8, String>,
+        replies: Vec<Reply>,
+    }
+
+    #[test]
+    fn matches_hand_written_layout() {
+        let params = SerialParams {
+            channel: 1,
+            baud: 19200,
+            char_len: CharLength::Eight,
+            parity: Parity::Even,
+            stop: StopBits::One,
+        };
+        let bytes = ser::to_bytes(&params).unwrap();
+        assert_eq!(bytes, [1, 0, 75, 0, 0, 8, 1, 1]);
+        assert_eq!(from_bytes::<SerialParams>(&bytes), Ok(params));
+
+        assert_eq!(
+            from_bytes::<SerialParams>(&[1, 0, 75, 0, 0, 9, 1, 1]),
+            Err(CerealError::InvalidFormat(String::from("invalid CharLength value 9")))
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let value = Everything {
+            flag: true,
+            letter: 'µ',
+            name: String::from("AT"),
+            scale: Some(0.5),
+            missing: None,
+            pair: (-1, 2),
+            nested: vec![vec![1], vec![], vec![2, 3]],
+            table: [(1, String::from("a")), (2, String::from("bc"))].into(),
+            replies: vec![
+                Reply::Ack,
+                Reply::Error(7),
+                Reply::Samples { channel: 1, data: vec![1024] },
+            ],
+        };
+
+        let bytes = ser::to_bytes(&value).unwrap();
+        assert_eq!(from_bytes::<Everything>(&bytes), Ok(value));
+
+        let mut padded = bytes.clone();
+        padded.push(0);
+        assert_eq!(
+            from_bytes::<Everything>(&padded),
+            Err(CerealError::TrailingBytes { count: 1 })
+        );
+        assert!(matches!(
+            from_bytes::<Everything>(&bytes[..bytes.len() - 1]),
+            Err(CerealError::Underflow { .. })
+        ));
+    }
+
+    #[test]
+    fn sequences_and_enums() {
+        let bytes = ser::to_bytes(&vec![Reply::Error(7), Reply::Ack]).unwrap();
+        assert_eq!(bytes, [4, 0, 1, 7, 0, 0]);
+
+        let mut stream = CerealStream::with_endian(Endian::Big);
+        ser::to_stream(&vec![1u16, 2], &mut stream).unwrap();
+        assert_eq!(stream.get_vec(), &[0, 4, 0, 1, 0, 2]);
+        assert_eq!(from_reader::<Vec<u16>>(&mut stream), Ok(vec![1, 2]));
+
+        // a u16 element cut in half by the length of its sequence
+        assert_eq!(
+            from_bytes::<Vec<u16>>(&[3, 0, 1, 0, 2, 0]),
+            Err(CerealError::InvalidFormat(String::from(
+                "element runs past the end of its sequence"
+            )))
+        );
+    }
+
+    #[test]
+    fn visitor_that_stops_early() {
+        // takes only the first element, however long the sequence says
+        // it is
+        #[derive(PartialEq, Debug)]
+        struct First(u16);
+
+        impl<'de> de::Deserialize<'de> for First {
+            fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct FirstVisitor;
+
+                impl<'de> Visitor<'de> for FirstVisitor {
+                    type Value = First;
+
+                    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        f.write_str("a sequence of u16")
+                    }
+
+                    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<First, A::Error> {
+                        let first = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                        Ok(First(first))
+                    }
+                }
+
+                deserializer.deserialize_seq(FirstVisitor)
+            }
+        }
+
+        assert_eq!(from_bytes::<First>(&[2, 0, 7, 0]), Ok(First(7)));
+        assert_eq!(
+            from_bytes::<First>(&[4, 0, 7, 0, 8, 0]),
+            Err(CerealError::TrailingBytes { count: 2 })
+        );
+        assert_eq!(
+            from_bytes::<First>(&[1, 0, 7, 0]),
+            Err(CerealError::InvalidFormat(String::from(
+                "element runs past the end of its sequence"
+            )))
+        );
+    }
+}
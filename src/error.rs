@@ -0,0 +1,147 @@
+use std::fmt;
+
+/// Everything that can go wrong while pouring cereal in or out.
+///
+/// Errors raised while a [`Packager`](super::cereal::Packager) handles a
+/// box are wrapped in [`CerealError::InBox`], which records the id of the
+/// box and the stream offset its frame started at. Use
+/// [`CerealError::root`] to match on the underlying failure.
+///
+/// # Examples
+///
+/// ```
+///   use open_channel::cereal::{CerealRead, CerealStream};
+///   use open_channel::error::CerealError;
+///
+///   let mut stream = CerealStream::new();
+///   stream.push_bytes(&[1]).unwrap();
+///
+///   assert_eq!(stream.try_pop_bytes(3), Err(CerealError::Underflow {
+///       requested: 3,
+///       available: 1,
+///   }));
+///
+///   let err = CerealError::InBox {
+///       id: 6,
+///       offset: 12,
+///       source: Box::new(CerealError::Underflow { requested: 3, available: 1 }),
+///   };
+///   assert!(matches!(err.root(), CerealError::Underflow { .. }));
+///   assert_eq!(
+///       err.to_string(),
+///       "box 6 at offset 12: stream underflow: requested 3 bytes but only 1 available"
+///   );
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CerealError {
+    /// A read asked for more bytes than the stream holds.
+    Underflow { requested: usize, available: usize },
+    /// A write would take a bounded stream past its limit.
+    Overflow { requested: usize, free: usize },
+    /// A frame started with an id that no registered box uses.
+    UnknownId { id: u8 },
+    /// A byte does not map to any value of the named enum.
+    InvalidEnumValue { name: &'static str, value: u8 },
+    /// A length prefixed field is longer than its prefix or the reader allows.
+    LengthOverflow { len: usize, max: usize },
+    /// A frame trailer does not match the checksum of its contents.
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// Bytes in front of the next frame sync marker were dropped.
+    LostSync { skipped: usize },
+    /// A box was poured in without using every byte of its frame.
+    TrailingBytes { count: usize },
+    /// A box needed more bytes than its length prefixed frame holds.
+    FrameTooShort { len: usize },
+    /// A box used a different number of bytes than its encoded length.
+    LengthMismatch { expected: usize, actual: usize },
+    /// A box does not know how to pour the requested layout version.
+    UnsupportedLayout { layout: u8 },
+    /// A varint ran past the ten bytes that can hold a 64 bit value.
+    VarintOverflow,
+    /// A length prefixed string is not valid UTF-8.
+    InvalidUtf8,
+    /// Input that is malformed in a way the other variants do not cover.
+    InvalidFormat(String),
+    /// An error raised while handling the box with the given id, whose
+    /// frame started at `offset` bytes into the stream.
+    InBox {
+        id: u8,
+        offset: usize,
+        source: Box<CerealError>,
+    },
+}
+
+impl CerealError {
+    /// Wraps the error with the id and stream offset of the box it was
+    /// raised for.
+    pub fn in_box(self, id: u8, offset: usize) -> Self {
+        Self::InBox {
+            id,
+            offset,
+            source: Box::new(self),
+        }
+    }
+
+    /// Returns the underlying error, looking through any box context.
+    pub fn root(&self) -> &CerealError {
+        match self {
+            Self::InBox { source, .. } => source.root(),
+            _ => self,
+        }
+    }
+}
+
+impl fmt::Display for CerealError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Underflow { requested, available } => write!(
+                f,
+                "stream underflow: requested {} bytes but only {} available",
+                requested, available
+            ),
+            Self::Overflow { requested, free } => write!(
+                f,
+                "stream overflow: writing {} bytes with only {} free",
+                requested, free
+            ),
+            Self::UnknownId { id } => write!(f, "unknown box id {}", id),
+            Self::InvalidEnumValue { name, value } => {
+                write!(f, "invalid {} value {}", name, value)
+            }
+            Self::LengthOverflow { len, max } => {
+                write!(f, "length {} exceeds the limit of {}", len, max)
+            }
+            Self::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "checksum mismatch: expected {:#x} but found {:#x}",
+                expected, actual
+            ),
+            Self::LostSync { skipped } => {
+                write!(f, "lost frame sync: skipped {} bytes", skipped)
+            }
+            Self::TrailingBytes { count } => write!(f, "{} trailing bytes left unread", count),
+            Self::FrameTooShort { len } => write!(f, "frame of {} bytes ended early", len),
+            Self::LengthMismatch { expected, actual } => write!(
+                f,
+                "length mismatch: expected {} bytes but used {}",
+                expected, actual
+            ),
+            Self::UnsupportedLayout { layout } => write!(f, "unsupported layout version {}", layout),
+            Self::VarintOverflow => write!(f, "varint does not fit in 64 bits"),
+            Self::InvalidUtf8 => write!(f, "string is not valid UTF-8"),
+            Self::InvalidFormat(reason) => write!(f, "{}", reason),
+            Self::InBox { id, offset, source } => {
+                write!(f, "box {} at offset {}: {}", id, offset, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CerealError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InBox { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
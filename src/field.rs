@@ -0,0 +1,156 @@
+use super::cereal::{CerealRead, CerealReader, CerealStream, LengthPrefix};
+use super::error::CerealError;
+use super::serial_params::{CharLength, Parity, StopBits};
+
+/// A value that knows how to pour itself in and out of the cereal wire
+/// format, so that it can be a field of a derived
+/// [`CerealBox`](super::cereal::CerealBox).
+///
+/// Numbers use the default byte order of the stream. Strings and `Vec`s
+/// are preceded by their length in bytes as a `u16`, the same layout
+/// `AdcData` uses for its samples.
+///
+/// # Examples
+///
+/// ```
+///   use open_channel::cereal::{CerealStream, CerealField};
+///
+///   let mut stream = CerealStream::new();
+///   let samples: Vec<i16> = vec![1024, -800];
+///   samples.pour_out_field(&mut stream).unwrap();
+///   assert_eq!(stream.get_vec(), &[4, 0, 0, 4, 224, 252]);
+///
+///   assert_eq!(Vec::<i16>::pour_in_field(&mut stream), Ok(samples));
+/// ```
+pub trait CerealField: Sized {
+    /// The number of bytes every value of the field takes on the wire, or
+    /// `None` if it depends on the value.
+    const FIXED_LEN: Option<usize> = None;
+
+    /// Pour the field into a cereal stream.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the stream has no room for
+    /// the field, or the field is too long for its length prefix.
+    fn pour_out_field(&self, package: &mut CerealStream) -> Result<(), CerealError>;
+
+    /// Pour a field out of a cereal source.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the source lacks sufficient
+    /// bytes, or they do not describe a valid value.
+    fn pour_in_field(package: &mut dyn CerealRead) -> Result<Self, CerealError>;
+}
+
+/// Implements [`CerealField`] for primitives with typed stream methods.
+macro_rules! primitive_fields {
+    ($($ty:ty: $push:ident, $pop:ident;)*) => {
+        $(
+            impl CerealField for $ty {
+                const FIXED_LEN: Option<usize> = Some(std::mem::size_of::<$ty>());
+
+                fn pour_out_field(&self, package: &mut CerealStream) -> Result<(), CerealError> {
+                    package.$push(*self)
+                }
+
+                fn pour_in_field(package: &mut dyn CerealRead) -> Result<Self, CerealError> {
+                    package.$pop()
+                }
+            }
+        )*
+    };
+}
+
+primitive_fields! {
+    u8: push_u8, try_pop_u8;
+    u16: push_u16, try_pop_u16;
+    u32: push_u32, try_pop_u32;
+    u64: push_u64, try_pop_u64;
+    i8: push_i8, try_pop_i8;
+    i16: push_i16, try_pop_i16;
+    i32: push_i32, try_pop_i32;
+    i64: push_i64, try_pop_i64;
+    f32: push_f32, try_pop_f32;
+    f64: push_f64, try_pop_f64;
+    bool: push_bool, try_pop_bool;
+}
+
+/// Implements [`CerealField`] for the serial setting enums through their
+/// single byte representation.
+macro_rules! byte_enum_fields {
+    ($($ty:ident),*) => {
+        $(
+            impl CerealField for $ty {
+                const FIXED_LEN: Option<usize> = Some(1);
+
+                fn pour_out_field(&self, package: &mut CerealStream) -> Result<(), CerealError> {
+                    package.push_u8(self.get_byte())
+                }
+
+                fn pour_in_field(package: &mut dyn CerealRead) -> Result<Self, CerealError> {
+                    let byte = package.try_pop_u8()?;
+                    $ty::from_byte(&byte).ok_or(CerealError::InvalidEnumValue {
+                        name: stringify!($ty),
+                        value: byte,
+                    })
+                }
+            }
+        )*
+    };
+}
+
+byte_enum_fields!(CharLength, Parity, StopBits);
+
+impl CerealField for String {
+    fn pour_out_field(&self, package: &mut CerealStream) -> Result<(), CerealError> {
+        package.push_str(self, LengthPrefix::U16)
+    }
+
+    fn pour_in_field(package: &mut dyn CerealRead) -> Result<Self, CerealError> {
+        package.try_pop_string(LengthPrefix::U16, u16::MAX as usize)
+    }
+}
+
+/// An optional trailing field of an extensible
+/// [`CerealBox`](super::cereal::CerealBox).
+///
+/// `None` is poured out as nothing at all, and poured back in when the
+/// source has run out of bytes, so only the last fields of a box can be
+/// optional.
+impl<T: CerealField> CerealField for Option<T> {
+    fn pour_out_field(&self, package: &mut CerealStream) -> Result<(), CerealError> {
+        match self {
+            Some(value) => value.pour_out_field(package),
+            None => Ok(()),
+        }
+    }
+
+    fn pour_in_field(package: &mut dyn CerealRead) -> Result<Self, CerealError> {
+        if package.is_empty() {
+            return Ok(None);
+        }
+        T::pour_in_field(package).map(Some)
+    }
+}
+
+impl<T: CerealField> CerealField for Vec<T> {
+    fn pour_out_field(&self, package: &mut CerealStream) -> Result<(), CerealError> {
+        let mut items = CerealStream::with_endian(package.endian());
+        for item in self {
+            item.pour_out_field(&mut items)?;
+        }
+        package.push_blob(items.get_vec(), LengthPrefix::U16)
+    }
+
+    fn pour_in_field(package: &mut dyn CerealRead) -> Result<Self, CerealError> {
+        let bytes = package.try_pop_blob(LengthPrefix::U16, u16::MAX as usize)?;
+        let mut items = CerealReader::with_endian(&bytes, package.endian());
+        let mut out = Vec::new();
+        while !items.is_empty() {
+            out.push(T::pour_in_field(&mut items)?);
+        }
+        Ok(out)
+    }
+}
@@ -0,0 +1,210 @@
+use super::cereal::{CerealRead, CerealReader, CerealStream, LengthPrefix};
+use super::checksum::Checksum;
+use super::cobs;
+use super::error::CerealError;
+
+/// The two bytes every [`Framing::Sync`] frame starts with.
+pub const SYNC: [u8; 2] = [0xA5, 0x5A];
+
+/// How a [`Packager`](super::cereal::Packager) wraps each `[id][payload]`
+/// frame on the wire, so a receiver can find frames again after bytes
+/// are lost or corrupted.
+///
+/// # Examples
+///
+/// ```
+///   use open_channel::cereal::{CerealBox, CerealRead, CerealStream, Packager};
+///   use open_channel::checksum::CRC16_CCITT;
+///   use open_channel::error::CerealError;
+///   use open_channel::frame::Framing;
+///
+///   #[derive(Default)]
+///   struct Channel(u8);
+///
+///   impl CerealBox for Channel {
+///       fn get_id(&self) -> u8 {
+///           2
+///       }
+///
+///       fn pour_out(&self, package: &mut CerealStream) -> Result<(), CerealError> {
+///           package.push_u8(self.0)
+///       }
+///
+///       fn pour_in(&mut self, package: &mut dyn CerealRead) -> Result<(), CerealError> {
+///           self.0 = package.try_pop_u8()?;
+///           Ok(())
+///       }
+///   }
+///
+///   let framing = Framing::Sync { checksum: &CRC16_CCITT, max_len: 64 };
+///
+///   let mut sender = Packager::new();
+///   sender.set_framing(Some(framing));
+///   sender.unpack(&Channel(5)).unwrap();
+///
+///   let mut wire = Vec::new();
+///   sender.write_to(&mut wire).unwrap();
+///   assert_eq!(wire, [0xA5, 0x5A, 2, 0, 2, 5, 0x6F, 0x5F]);
+///
+///   let mut receiver = Packager::new();
+///   receiver.add_flavor::<Channel>();
+///   receiver.set_framing(Some(framing));
+///
+///   // line noise ahead of the frame is skipped over
+///   receiver.push_bytes(&[0x13, 0x37]).unwrap();
+///   receiver.push_bytes(&wire).unwrap();
+///   assert_eq!(receiver.pack().unwrap_err(), CerealError::LostSync { skipped: 2 });
+///   assert_eq!(receiver.pack().unwrap().downcast_ref::<Channel>().unwrap().0, 5);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub enum Framing {
+    /// `[SYNC][length][id][payload][checksum]`, where the length is a `u16`
+    /// count of the id and payload bytes, and the checksum covers the
+    /// length, id and payload.
+    ///
+    /// A length over `max_len` is taken as a corrupted frame rather than
+    /// waited for, so it should be the longest frame the link carries.
+    Sync {
+        checksum: &'static dyn Checksum,
+        max_len: usize,
+    },
+    /// The frame encoded with Consistent Overhead Byte Stuffing and
+    /// followed by a [`cobs::DELIMITER`], which appears nowhere else on
+    /// the wire. Any bytes a frame loses or gains only spoil that frame.
+    Cobs,
+}
+
+impl Framing {
+    /// Returns how many bytes the framing adds around a frame of `len`
+    /// bytes.
+    pub fn overhead(&self, len: usize) -> usize {
+        match self {
+            Self::Sync { checksum, .. } => SYNC.len() + 2 + checksum.width(),
+            Self::Cobs => cobs::max_overhead(len) + 1,
+        }
+    }
+
+    /// Wraps the `[id][payload]` bytes of one frame and pushes them into
+    /// `out`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::LengthOverflow`] if the frame is too long
+    /// for the length field or the `max_len` of [`Framing::Sync`], or
+    /// [`CerealError::Overflow`] if `out` is too full. Nothing is pushed in
+    /// either case.
+    pub fn encode(&self, frame: &[u8], out: &mut CerealStream) -> Result<(), CerealError> {
+        match self {
+            Self::Sync { checksum, max_len } => {
+                if frame.len() > *max_len {
+                    return Err(CerealError::LengthOverflow { len: frame.len(), max: *max_len });
+                }
+                let mut body = CerealStream::with_endian(out.endian());
+                body.push_blob(frame, LengthPrefix::U16)?;
+                push_checksum(&mut body, *checksum)?;
+                let mut framed = CerealStream::with_endian(out.endian());
+                framed.push_bytes(&SYNC)?;
+                framed.push_bytes(body.get_vec())?;
+                out.push_bytes(framed.get_vec())
+            }
+            Self::Cobs => {
+                let mut framed = cobs::encode(frame);
+                framed.push(cobs::DELIMITER);
+                out.push_bytes(&framed)
+            }
+        }
+    }
+
+    /// Takes the next frame out of `input` and returns its `[id][payload]`
+    /// bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Underflow`] if `input` only holds the start
+    /// of a frame. Nothing is consumed in that case.
+    ///
+    /// Returns [`CerealError::LostSync`] after dropping any bytes in front
+    /// of the next sync marker. A frame whose length is over its
+    /// `max_len` fails with [`CerealError::LengthOverflow`], and one that
+    /// fails its checksum with [`CerealError::ChecksumMismatch`], after
+    /// dropping its first byte. Either way the next call starts looking
+    /// for a frame after them.
+    ///
+    /// A [`Framing::Cobs`] frame that does not decode is dropped up to
+    /// its delimiter, and fails with [`CerealError::InvalidFormat`].
+    /// Each call looks for the delimiter from the start of `input` again,
+    /// so a [`Packager`](super::cereal::Packager) decodes COBS frames
+    /// with a [`CobsDecoder`](super::cobs::CobsDecoder) instead.
+    pub fn decode(&self, input: &mut dyn CerealRead) -> Result<Vec<u8>, CerealError> {
+        match self {
+            Self::Sync { checksum, max_len } => {
+                let skipped = find_sync(input.remaining());
+                if skipped > 0 {
+                    input.skip(skipped)?;
+                    return Err(CerealError::LostSync { skipped });
+                }
+
+                let mut reader = CerealReader::with_endian(input.remaining(), input.endian());
+                reader.skip(SYNC.len())?;
+                let len = reader.try_pop_u16()? as usize;
+                if len > *max_len {
+                    input.skip(1)?;
+                    return Err(CerealError::LengthOverflow { len, max: *max_len });
+                }
+                let frame = reader.try_pop_slice(len)?.to_vec();
+                let actual = pop_checksum(&mut reader, *checksum)?;
+                let expected = checksum.checksum(&input.remaining()[SYNC.len()..SYNC.len() + 2 + len]);
+                let used = reader.position();
+                if expected != actual {
+                    input.skip(1)?;
+                    return Err(CerealError::ChecksumMismatch { expected, actual });
+                }
+                input.skip(used)?;
+                Ok(frame)
+            }
+            Self::Cobs => {
+                let empty = input.remaining().iter().take_while(|&&byte| byte == cobs::DELIMITER).count();
+                input.skip(empty)?;
+                let available = input.len();
+                let len = input
+                    .remaining()
+                    .iter()
+                    .position(|&byte| byte == cobs::DELIMITER)
+                    .ok_or(CerealError::Underflow { requested: available + 1, available })?;
+                let frame = cobs::decode(&input.remaining()[..len]);
+                input.skip(len + 1)?;
+                frame
+            }
+        }
+    }
+}
+
+/// Returns how many bytes come before the first place a sync marker
+/// starts, counting a partial marker at the very end as a start.
+fn find_sync(bytes: &[u8]) -> usize {
+    (0..bytes.len())
+        .find(|&i| {
+            let rest = &bytes[i..];
+            let n = rest.len().min(SYNC.len());
+            rest[..n] == SYNC[..n]
+        })
+        .unwrap_or(bytes.len())
+}
+
+/// Pushes the checksum of everything in `body` onto its end.
+fn push_checksum(body: &mut CerealStream, checksum: &dyn Checksum) -> Result<(), CerealError> {
+    let value = checksum.checksum(body.get_vec());
+    match checksum.width() {
+        1 => body.push_u8(value as u8),
+        2 => body.push_u16(value as u16),
+        _ => body.push_u32(value),
+    }
+}
+
+fn pop_checksum(reader: &mut dyn CerealRead, checksum: &dyn Checksum) -> Result<u32, CerealError> {
+    match checksum.width() {
+        1 => reader.try_pop_u8().map(u32::from),
+        2 => reader.try_pop_u16().map(u32::from),
+        _ => reader.try_pop_u32(),
+    }
+}
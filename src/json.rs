@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::cereal::CerealBox;
+use super::error::CerealError;
+
+/// Turns a box into the JSON value of its fields.
+type ToJson = fn(&dyn CerealBox) -> Result<Value, CerealError>;
+
+/// Builds a box from the JSON value of its fields.
+type FromJson = fn(Value) -> Result<Box<dyn CerealBox>, CerealError>;
+
+struct Flavor {
+    name: &'static str,
+    to_json: ToJson,
+    from_json: FromJson,
+}
+
+/// Renders boxes as JSON and parses them back, for logging and test
+/// fixtures.
+///
+/// A box is written as an object with a single key, the name of its type,
+/// holding its fields: `{"AdcData":{"channel":7,"data":[1024]}}`. When
+/// parsing, the key can also be the id of the box, such as `"6"`.
+///
+/// # Examples
+///
+/// ```
+///   use open_channel::cereal::Packager;
+///   use open_channel::json::JsonRegistry;
+///   use open_channel::serde_box;
+///   use serde_derive::{Deserialize, Serialize};
+///
+///   #[derive(Serialize, Deserialize, Default, PartialEq, Debug)]
+///   struct AdcData {
+///       channel: u8,
+///       data: Vec<i16>,
+///   }
+///
+///   serde_box!(AdcData, 6);
+///
+///   let mut registry = JsonRegistry::new();
+///   registry.add::<AdcData>();
+///
+///   let adc = AdcData { channel: 7, data: vec![1024, -800] };
+///   let json = registry.to_json_string(&adc).unwrap();
+///   assert_eq!(json, r#"{"AdcData":{"channel":7,"data":[1024,-800]}}"#);
+///
+///   let parsed = registry.from_json_str(&json).unwrap();
+///   assert_eq!(parsed.downcast_ref::<AdcData>(), Some(&adc));
+///
+///   let by_id = registry.from_json_str(r#"{"6":{"channel":7,"data":[1024,-800]}}"#).unwrap();
+///   let mut sent = Packager::new();
+///   let mut parsed = Packager::new();
+///   sent.unpack(&adc).unwrap();
+///   parsed.unpack(by_id.as_ref()).unwrap();
+///
+///   let (mut sent_bytes, mut parsed_bytes) = (Vec::new(), Vec::new());
+///   sent.write_to(&mut sent_bytes).unwrap();
+///   parsed.write_to(&mut parsed_bytes).unwrap();
+///   assert_eq!(sent_bytes, parsed_bytes);
+/// ```
+#[derive(Default)]
+pub struct JsonRegistry {
+    flavors: HashMap<u8, Flavor>,
+}
+
+impl JsonRegistry {
+    /// Creates a new, empty [`JsonRegistry`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a box flavor, named after its type.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a flavor with the same id or name has already been added.
+    pub fn add<T>(&mut self)
+    where
+        T: CerealBox + Serialize + DeserializeOwned + Default + 'static,
+    {
+        let id = T::default().get_id();
+        let name = short_type_name::<T>();
+        if self.flavors.contains_key(&id) {
+            panic!("Error adding flavor to JsonRegistry! Multiple flavors have id: {}", id);
+        }
+        if self.flavors.values().any(|flavor| flavor.name == name) {
+            panic!("Error adding flavor to JsonRegistry! Multiple flavors have name: {}", name);
+        }
+        self.flavors.insert(id, Flavor {
+            name,
+            to_json: |msg| {
+                let msg = msg
+                    .downcast_ref::<T>()
+                    .ok_or(CerealError::UnknownId { id: msg.get_id() })?;
+                serde_json::to_value(msg).map_err(json_error)
+            },
+            from_json: |fields| {
+                let msg: T = serde_json::from_value(fields).map_err(json_error)?;
+                Ok(Box::new(msg))
+            },
+        });
+    }
+
+    /// Renders a box as a JSON value keyed by the name of its type.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::UnknownId`] if the flavor of the box has not
+    /// been added.
+    pub fn to_json(&self, msg: &dyn CerealBox) -> Result<Value, CerealError> {
+        let id = msg.get_id();
+        let flavor = self.flavors.get(&id).ok_or(CerealError::UnknownId { id })?;
+        let mut object = Map::new();
+        object.insert(String::from(flavor.name), (flavor.to_json)(msg)?);
+        Ok(Value::Object(object))
+    }
+
+    /// Renders a box as a JSON string keyed by the name of its type.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::UnknownId`] if the flavor of the box has not
+    /// been added.
+    pub fn to_json_string(&self, msg: &dyn CerealBox) -> Result<String, CerealError> {
+        Ok(self.to_json(msg)?.to_string())
+    }
+
+    /// Builds a box from a JSON value keyed by the name or id of its
+    /// flavor.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::UnknownId`] for an id that has not been
+    /// added, or [`CerealError::InvalidFormat`] if the value is not a
+    /// single keyed object holding valid fields for the flavor.
+    pub fn from_json(&self, value: Value) -> Result<Box<dyn CerealBox>, CerealError> {
+        let object = match value {
+            Value::Object(object) if object.len() == 1 => object,
+            _ => {
+                return Err(CerealError::InvalidFormat(String::from(
+                    "a box must be an object with a single key",
+                )))
+            }
+        };
+        let (key, fields) = object.into_iter().next().expect("object has one key");
+        let flavor = match key.parse::<u8>() {
+            Ok(id) => self.flavors.get(&id).ok_or(CerealError::UnknownId { id })?,
+            Err(_) => self
+                .flavors
+                .values()
+                .find(|flavor| flavor.name == key)
+                .ok_or_else(|| CerealError::InvalidFormat(format!("unknown box name {}", key)))?,
+        };
+        (flavor.from_json)(fields)
+    }
+
+    /// Builds a box from a JSON string keyed by the name or id of its
+    /// flavor.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::InvalidFormat`] if the string is not valid
+    /// JSON, or any error of [`JsonRegistry::from_json`].
+    pub fn from_json_str(&self, json: &str) -> Result<Box<dyn CerealBox>, CerealError> {
+        self.from_json(serde_json::from_str(json).map_err(json_error)?)
+    }
+}
+
+fn json_error(err: serde_json::Error) -> CerealError {
+    CerealError::InvalidFormat(err.to_string())
+}
+
+/// The name of a type without its module path or generic parameters.
+fn short_type_name<T>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
+}
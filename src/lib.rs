@@ -1,3 +1,14 @@
 pub mod serial_params;
-pub mod message;
 pub mod cereal;
+pub mod error;
+pub mod bits;
+pub mod ser;
+pub mod de;
+pub mod json;
+pub mod frame;
+pub mod checksum;
+pub mod cobs;
+#[cfg(feature = "std-messages")]
+pub mod boxes;
+mod reader;
+mod field;
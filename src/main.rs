@@ -1,54 +1,22 @@
-mod boxes;
-use boxes::{*};
+use open_channel::boxes::{*};
 use open_channel::cereal::Packager;
 use open_channel::serial_params::{CharLength, Parity, StopBits};
 
-impl Ping{
-    fn consume(&self) {
-        println!("Ping  Consuming: {:?}", self);
-    }
-}
-impl Pong{
-    fn consume(&self) {
-        println!("Pong  Consuming: {:?}", self);
-    }
-}
-impl VersionQuery{
-    fn consume(&self) {
-        println!("VersionQuery  Consuming: {:?}", self);
-    }
-}
-impl VersionData{
-    fn consume(&self) {
-        println!("VersionData  Consuming: {:?}", self);
-    }
-}
-impl AdcQuery{
-    fn consume(&self) {
-        println!("AdcQuery  Consuming: {:?}", self);
-    }
-}
-impl AdcData{
-    fn consume(&self) {
-        println!("AdcData  Consuming: {:?}", self);
-    }
-}
-impl SerialParams{
-    fn consume(&self) {
-        println!("SerialParams  Consuming: {:?}", self);
-    }
-}
-
-
 fn create_packger() -> Packager {
-    let mut packager = Packager::new();
-    packager.add_flavor(Box::new(Ping::default()));
-    packager.add_flavor(Box::new(Pong::default()));
-    packager.add_flavor(Box::new(VersionQuery::default()));
-    packager.add_flavor(Box::new(VersionData::default()));
-    packager.add_flavor(Box::new(AdcQuery::default()));
-    packager.add_flavor(Box::new(AdcData::default()));
-    packager.add_flavor(Box::new(SerialParams::default()));
+    let mut packager = Packager::with_std_messages();
+    packager.on::<Ping>(|msg| println!("Ping  Consuming: {:?}", msg));
+    packager.on::<Pong>(|msg| println!("Pong  Consuming: {:?}", msg));
+    packager.on::<VersionQuery>(|msg| println!("VersionQuery  Consuming: {:?}", msg));
+    packager.on::<VersionData>(|msg| println!("VersionData  Consuming: {:?}", msg));
+    packager.on::<AdcQuery>(|msg| println!("AdcQuery  Consuming: {:?}", msg));
+
+    let mut samples = 0;
+    packager.on::<AdcData>(move |msg| {
+        samples += msg.data.len();
+        println!("AdcData  Consuming: {:?} ({} samples so far)", msg, samples);
+    });
+    packager.on::<SerialParams>(|msg| println!("SerialParams  Consuming: {:?}", msg));
+    packager.on::<SerialData>(|msg| println!("SerialData  Consuming: {:?}", msg));
     packager
 }
 
@@ -57,42 +25,53 @@ fn main() {
 
     let mut packager = create_packger();
 
-    packager.unpack(&Ping::default());
-    packager.unpack(&Pong::default());
-    packager.unpack(&VersionQuery::default());
+    packager.unpack(&Ping::default()).unwrap();
+    packager.unpack(&Pong::default()).unwrap();
+    packager.unpack(&VersionQuery::default()).unwrap();
     packager.unpack(&VersionData{
         major: 1,
         minor: 2,
         maintenance: 3,
         build: 4,
-    });
+    }).unwrap();
     packager.unpack(&VersionData{
         major: 4,
         minor: 1,
         maintenance: 9,
         build: 3,
-    });
+    }).unwrap();
     packager.unpack(&SerialParams{
         channel: 1,
         baud: 19200,
         char_len: CharLength::Eight,
         parity: Parity::Even,
         stop: StopBits::One
-    });
+    }).unwrap();
     packager.unpack(&AdcQuery{
         channel: 3,
         length:4,
         increment_usec: 1500
-    });
+    }).unwrap();
     packager.unpack(&AdcData{
         channel: 7,
         data: [1024, 1999, 0, -800, -900].to_vec()
-    });
-    packager.unpack(&SerialParams::from_str(2, "9600:8O2").unwrap());
-    packager.unpack(&SerialParams::from_str(3, "4800:7n1").unwrap());
+    }).unwrap();
+    packager.unpack(&SerialParams::from_str(2, "9600:8O2").unwrap()).unwrap();
+    packager.unpack(&SerialParams::from_str(3, "4800:7n1").unwrap()).unwrap();
+    packager.unpack(&SerialData{
+        channel: 2,
+        data: String::from("AT\r")
+    }).unwrap();
+    packager.unpack(&Status{
+        rcv_count: 260,
+        snd_count: 270,
+        rcv_fails: 1
+    }).unwrap();
 
-    while !packager.is_empty(){
-        packager.pack().unwrap();
+    for msg in packager.drain_messages() {
+        if let Ok(Message::Status(status)) = Message::from_box(msg.unwrap()) {
+            println!("Status  {} of {} frames failed", status.rcv_fails, status.rcv_count);
+        }
     }
 
 }
@@ -0,0 +1,449 @@
+use super::cereal::{Endian, LengthPrefix};
+use super::error::CerealError;
+
+/// Generates the default order, little endian and big endian
+/// pop methods for a primitive type.
+macro_rules! pop_methods {
+    ($($ty:ty: $pop:ident, $pop_le:ident, $pop_be:ident;)*) => {
+        $(
+            #[doc = concat!("Returns a `", stringify!($ty), "` read in the default byte order of the source.")]
+            ///
+            /// # Errors
+            ///
+            /// Returns [`CerealError::Underflow`] if the source is too short.
+            fn $pop(&mut self) -> Result<$ty, CerealError> {
+                match self.endian() {
+                    Endian::Little => self.$pop_le(),
+                    Endian::Big => self.$pop_be(),
+                }
+            }
+
+            #[doc = concat!("Returns a little endian `", stringify!($ty), "`.")]
+            ///
+            /// # Errors
+            ///
+            /// Returns [`CerealError::Underflow`] if the source is too short.
+            fn $pop_le(&mut self) -> Result<$ty, CerealError> {
+                let mut bytes = [0; std::mem::size_of::<$ty>()];
+                self.try_pop_into(&mut bytes)?;
+                Ok(<$ty>::from_le_bytes(bytes))
+            }
+
+            #[doc = concat!("Returns a big endian `", stringify!($ty), "`.")]
+            ///
+            /// # Errors
+            ///
+            /// Returns [`CerealError::Underflow`] if the source is too short.
+            fn $pop_be(&mut self) -> Result<$ty, CerealError> {
+                let mut bytes = [0; std::mem::size_of::<$ty>()];
+                self.try_pop_into(&mut bytes)?;
+                Ok(<$ty>::from_be_bytes(bytes))
+            }
+        )*
+    };
+}
+
+/// The read side of the cereal wire format.
+///
+/// Implemented by the owned [`CerealStream`](super::cereal::CerealStream)
+/// and the borrowed [`CerealReader`], so a [`CerealBox`](super::cereal::CerealBox)
+/// can be poured in from either.
+pub trait CerealRead {
+    /// Returns the unread bytes.
+    fn remaining(&self) -> &[u8];
+
+    /// Returns the default byte order used by the typed readers.
+    fn endian(&self) -> Endian;
+
+    /// Consumes `num_bytes` of the unread bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Underflow`] if there are not enough bytes.
+    /// Nothing is consumed in that case.
+    fn skip(&mut self, num_bytes: usize) -> Result<(), CerealError>;
+
+    /// Remembers the current read position so that it can be returned to
+    /// with [`CerealRead::rollback`].
+    ///
+    /// Marks nest; every mark must be closed by either a rollback or a
+    /// [`CerealRead::commit`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///   use open_channel::cereal::{CerealRead, CerealStream};
+    ///
+    ///   let mut stream = CerealStream::new();
+    ///   stream.push_bytes(&[1, 2, 3]).unwrap();
+    ///
+    ///   stream.mark();
+    ///   assert_eq!(stream.try_pop_u16(), Ok(0x0201));
+    ///   assert!(stream.try_pop_u16().is_err());
+    ///   stream.rollback();
+    ///   assert_eq!(stream.get_vec(), &[1, 2, 3]);
+    ///
+    ///   stream.mark();
+    ///   assert_eq!(stream.peek_byte(), Ok(1));
+    ///   assert_eq!(stream.try_pop_bytes(2), Ok(vec![1, 2]));
+    ///   stream.commit();
+    ///   assert_eq!(stream.get_vec(), &[3]);
+    /// ```
+    fn mark(&mut self);
+
+    /// Returns to the read position of the most recent mark.
+    ///
+    /// Does nothing if there is no outstanding mark.
+    fn rollback(&mut self);
+
+    /// Accepts everything read since the most recent mark.
+    ///
+    /// Does nothing if there is no outstanding mark.
+    fn commit(&mut self);
+
+    /// Returns the number of unread bytes.
+    fn len(&self) -> usize {
+        self.remaining().len()
+    }
+
+    /// Returns true if there is nothing left to read.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the next byte without consuming it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Underflow`] if there is nothing left to read.
+    fn peek_byte(&self) -> Result<u8, CerealError> {
+        Ok(self.peek_bytes(1)?[0])
+    }
+
+    /// Returns the next `num_bytes` without consuming them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Underflow`] if there are not enough bytes.
+    fn peek_bytes(&self, num_bytes: usize) -> Result<&[u8], CerealError> {
+        let remaining = self.remaining();
+        if remaining.len() < num_bytes {
+            return Err(CerealError::Underflow {
+                requested: num_bytes,
+                available: remaining.len(),
+            });
+        }
+        Ok(&remaining[..num_bytes])
+    }
+
+    /// Fills `out` with the next bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Underflow`] if there are not enough bytes.
+    /// Nothing is consumed in that case.
+    fn try_pop_into(&mut self, out: &mut [u8]) -> Result<(), CerealError> {
+        out.copy_from_slice(self.peek_bytes(out.len())?);
+        self.skip(out.len())
+    }
+
+    /// Returns a single byte.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Underflow`] if there is nothing left to read.
+    fn try_pop_byte(&mut self) -> Result<u8, CerealError> {
+        let byte = self.peek_byte()?;
+        self.skip(1)?;
+        Ok(byte)
+    }
+
+    /// Returns the specified number of bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Underflow`] if there are not enough bytes.
+    /// Nothing is consumed in that case.
+    fn try_pop_bytes(&mut self, num_bytes: usize) -> Result<Vec<u8>, CerealError> {
+        let out = self.peek_bytes(num_bytes)?.to_vec();
+        self.skip(num_bytes)?;
+        Ok(out)
+    }
+
+    /// Returns the next `N` bytes as a fixed size array.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Underflow`] if there are not enough bytes.
+    /// Nothing is consumed in that case.
+    fn try_pop_array<const N: usize>(&mut self) -> Result<[u8; N], CerealError>
+    where
+        Self: Sized,
+    {
+        let mut out = [0; N];
+        self.try_pop_into(&mut out)?;
+        Ok(out)
+    }
+
+    /// Returns a `u8`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Underflow`] if there is nothing left to read.
+    fn try_pop_u8(&mut self) -> Result<u8, CerealError> {
+        self.try_pop_byte()
+    }
+
+    /// Returns an `i8`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Underflow`] if there is nothing left to read.
+    fn try_pop_i8(&mut self) -> Result<i8, CerealError> {
+        Ok(self.try_pop_byte()? as i8)
+    }
+
+    /// Returns a `bool`, treating any non zero byte as true.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Underflow`] if there is nothing left to read.
+    fn try_pop_bool(&mut self) -> Result<bool, CerealError> {
+        Ok(self.try_pop_byte()? != 0)
+    }
+
+    /// Returns a `u64` read as an unsigned LEB128 varint.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Underflow`] if the varint is cut short and
+    /// [`CerealError::VarintOverflow`] if it does not fit in 64 bits.
+    /// Nothing is consumed in either case.
+    fn try_pop_varint_u64(&mut self) -> Result<u64, CerealError> {
+        let mut value: u64 = 0;
+        for (i, &byte) in self.remaining().iter().enumerate() {
+            let shift = 7 * i as u32;
+            if shift > 63 || (shift == 63 && byte & 0x7f > 1) {
+                return Err(CerealError::VarintOverflow);
+            }
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                self.skip(i + 1)?;
+                return Ok(value);
+            }
+        }
+        Err(CerealError::Underflow {
+            requested: self.len() + 1,
+            available: self.len(),
+        })
+    }
+
+    /// Returns an `i64` read as a zigzag encoded LEB128 varint.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Underflow`] if the varint is cut short and
+    /// [`CerealError::VarintOverflow`] if it does not fit in 64 bits.
+    /// Nothing is consumed in either case.
+    fn try_pop_varint_i64(&mut self) -> Result<i64, CerealError> {
+        let value = self.try_pop_varint_u64()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    /// Returns a byte blob preceded by its length.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::LengthOverflow`] if the length is more than
+    /// `max_len`, or [`CerealError::Underflow`] if the blob is cut short.
+    /// Nothing is consumed in either case.
+    fn try_pop_blob(&mut self, prefix: LengthPrefix, max_len: usize) -> Result<Vec<u8>, CerealError> {
+        self.mark();
+        let result = self.try_pop_len(prefix, max_len).and_then(|len| self.try_pop_bytes(len));
+        match result {
+            Ok(_) => self.commit(),
+            Err(_) => self.rollback(),
+        }
+        result
+    }
+
+    /// Returns a UTF-8 string preceded by its length in bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`CerealRead::try_pop_blob`], or
+    /// [`CerealError::InvalidUtf8`] if the bytes are not a valid string.
+    /// Nothing is consumed in any of these cases.
+    fn try_pop_string(&mut self, prefix: LengthPrefix, max_len: usize) -> Result<String, CerealError> {
+        self.mark();
+        let result = self
+            .try_pop_blob(prefix, max_len)
+            .and_then(|bytes| String::from_utf8(bytes).map_err(|_| CerealError::InvalidUtf8));
+        match result {
+            Ok(_) => self.commit(),
+            Err(_) => self.rollback(),
+        }
+        result
+    }
+
+    /// Returns a length prefix, checked against `max_len`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::LengthOverflow`] if the length is more than
+    /// `max_len`, or [`CerealError::Underflow`] if the prefix is cut short.
+    fn try_pop_len(&mut self, prefix: LengthPrefix, max_len: usize) -> Result<usize, CerealError> {
+        let len = match prefix {
+            LengthPrefix::U8 => self.try_pop_u8()? as usize,
+            LengthPrefix::U16 => self.try_pop_u16()? as usize,
+            LengthPrefix::U32 => self.try_pop_u32()? as usize,
+            LengthPrefix::Varint => usize::try_from(self.try_pop_varint_u64()?)
+                .map_err(|_| CerealError::LengthOverflow { len: usize::MAX, max: max_len })?,
+        };
+        if len > max_len {
+            return Err(CerealError::LengthOverflow { len, max: max_len });
+        }
+        Ok(len)
+    }
+
+    pop_methods! {
+        u16: try_pop_u16, try_pop_u16_le, try_pop_u16_be;
+        u32: try_pop_u32, try_pop_u32_le, try_pop_u32_be;
+        u64: try_pop_u64, try_pop_u64_le, try_pop_u64_be;
+        i16: try_pop_i16, try_pop_i16_le, try_pop_i16_be;
+        i32: try_pop_i32, try_pop_i32_le, try_pop_i32_be;
+        i64: try_pop_i64, try_pop_i64_le, try_pop_i64_be;
+        f32: try_pop_f32, try_pop_f32_le, try_pop_f32_be;
+        f64: try_pop_f64, try_pop_f64_le, try_pop_f64_be;
+    }
+}
+
+/// A zero copy reader over a borrowed byte slice, such as a DMA buffer.
+///
+/// # Examples
+///
+/// ```
+///   use open_channel::cereal::{CerealRead, CerealReader};
+///
+///   let dma = [6, 7, 4, 0, 1, 2, 3, 4];
+///   let mut reader = CerealReader::new(&dma);
+///
+///   assert_eq!(reader.try_pop_byte(), Ok(6));
+///   assert_eq!(reader.try_pop_byte(), Ok(7));
+///   let length = reader.try_pop_u16().unwrap() as usize;
+///   let data: &[u8] = reader.try_pop_slice(length).unwrap();
+///   assert_eq!(data, &[1, 2, 3, 4]);
+///   assert!(reader.is_empty());
+/// ```
+pub struct CerealReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    marks: Vec<usize>,
+    endian: Endian,
+}
+
+impl<'a> CerealReader<'a> {
+    /// Creates a new [`CerealReader`] over `data`.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self::with_endian(data, Endian::default())
+    }
+
+    /// Creates a new [`CerealReader`] over `data` whose typed readers
+    /// default to the given byte order.
+    pub fn with_endian(data: &'a [u8], endian: Endian) -> Self {
+        Self {
+            data,
+            pos: 0,
+            marks: Vec::new(),
+            endian,
+        }
+    }
+
+    /// Returns the number of bytes read so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns the next `num_bytes` as a sub slice of the underlying data.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CerealError::Underflow`] if there are not enough bytes.
+    /// Nothing is consumed in that case.
+    pub fn try_pop_slice(&mut self, num_bytes: usize) -> Result<&'a [u8], CerealError> {
+        self.peek_bytes(num_bytes)?;
+        let out = &self.data[self.pos..self.pos + num_bytes];
+        self.pos += num_bytes;
+        Ok(out)
+    }
+}
+
+impl CerealRead for CerealReader<'_> {
+    fn remaining(&self) -> &[u8] {
+        &self.data[self.pos..]
+    }
+
+    fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    fn skip(&mut self, num_bytes: usize) -> Result<(), CerealError> {
+        self.peek_bytes(num_bytes)?;
+        self.pos += num_bytes;
+        Ok(())
+    }
+
+    fn mark(&mut self) {
+        self.marks.push(self.pos);
+    }
+
+    fn rollback(&mut self) {
+        if let Some(pos) = self.marks.pop() {
+            self.pos = pos;
+        }
+    }
+
+    fn commit(&mut self) {
+        self.marks.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cereal::CerealStream;
+
+    #[test]
+    fn varint_limits() {
+        let mut stream = CerealStream::new();
+
+        for value in [0, 127, 128, u32::MAX as u64, u64::MAX] {
+            stream.push_varint_u64(value).unwrap();
+            assert_eq!(stream.try_pop_varint_u64(), Ok(value));
+        }
+        for value in [0, -1, 1, i64::MIN, i64::MAX] {
+            stream.push_varint_i64(value).unwrap();
+            assert_eq!(stream.try_pop_varint_i64(), Ok(value));
+        }
+        assert!(stream.is_empty());
+    }
+
+    #[test]
+    fn varint_errors() {
+        let short = [0x80, 0x80];
+        let mut reader = CerealReader::new(&short);
+        assert_eq!(
+            reader.try_pop_varint_u64(),
+            Err(CerealError::Underflow { requested: 3, available: 2 })
+        );
+        assert_eq!(reader.position(), 0);
+
+        let long = [0xff; 11];
+        let mut reader = CerealReader::new(&long);
+        assert_eq!(reader.try_pop_varint_u64(), Err(CerealError::VarintOverflow));
+
+        let wide = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02];
+        let mut reader = CerealReader::new(&wide);
+        assert_eq!(reader.try_pop_varint_u64(), Err(CerealError::VarintOverflow));
+    }
+}
@@ -0,0 +1,349 @@
+use serde::ser::{self, Serialize};
+
+use super::cereal::{CerealStream, LengthPrefix};
+use super::error::CerealError;
+
+/// Pours any [`Serialize`] value into a cereal stream.
+///
+/// Values are laid out the way the hand written boxes lay them out:
+///
+/// * numbers use the byte order of the stream and `bool` is one byte
+/// * `char` is its `u32` code point
+/// * strings, byte strings, sequences and maps are preceded by their
+///   length in bytes as a `u16`
+/// * `Option` is a `0` or `1` byte followed by the value, if any
+/// * structs and tuples are their fields in order, with nothing around them
+/// * enum variants are their index as a `u8` followed by their fields
+///
+/// # Examples
+///
+/// ```
+///   use open_channel::cereal::CerealStream;
+///   use open_channel::ser;
+///   use serde_derive::Serialize;
+///
+///   #[derive(Serialize)]
+///   struct AdcData {
+///       channel: u8,
+///       data: Vec<i16>,
+///   }
+///
+///   let adc = AdcData { channel: 1, data: vec![1024, -800] };
+///   let mut stream = CerealStream::new();
+///   ser::to_stream(&adc, &mut stream).unwrap();
+///   assert_eq!(stream.get_vec(), &[1, 4, 0, 0, 4, 224, 252]);
+/// ```
+///
+/// # Errors
+///
+/// This function will return an error if the stream has no room for the
+/// value, or a length does not fit in its `u16` prefix.
+pub fn to_stream<T: Serialize + ?Sized>(value: &T, stream: &mut CerealStream) -> Result<(), CerealError> {
+    value.serialize(&mut Serializer { output: stream })
+}
+
+/// Pours any [`Serialize`] value into a new byte vector, using the
+/// default byte order.
+///
+/// # Errors
+///
+/// This function will return an error if a length does not fit in its
+/// `u16` prefix.
+pub fn to_bytes<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, CerealError> {
+    let mut stream = CerealStream::new();
+    to_stream(value, &mut stream)?;
+    Ok(stream.get_vec().to_vec())
+}
+
+/// A serde [`Serializer`](ser::Serializer) that writes the cereal wire
+/// format into a [`CerealStream`].
+pub struct Serializer<'a> {
+    output: &'a mut CerealStream,
+}
+
+impl<'a> Serializer<'a> {
+    /// Creates a new [`Serializer`] that pushes into `output`.
+    pub fn new(output: &'a mut CerealStream) -> Self {
+        Self { output }
+    }
+}
+
+impl ser::Error for CerealError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        CerealError::InvalidFormat(msg.to_string())
+    }
+}
+
+impl<'a, 'b> ser::Serializer for &'b mut Serializer<'a> {
+    type Ok = ();
+    type Error = CerealError;
+
+    type SerializeSeq = Prefixed<'a, 'b>;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Prefixed<'a, 'b>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<(), CerealError> {
+        self.output.push_bool(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), CerealError> {
+        self.output.push_i8(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), CerealError> {
+        self.output.push_i16(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), CerealError> {
+        self.output.push_i32(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), CerealError> {
+        self.output.push_i64(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), CerealError> {
+        self.output.push_u8(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), CerealError> {
+        self.output.push_u16(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), CerealError> {
+        self.output.push_u32(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), CerealError> {
+        self.output.push_u64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), CerealError> {
+        self.output.push_f32(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), CerealError> {
+        self.output.push_f64(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), CerealError> {
+        self.output.push_u32(v as u32)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), CerealError> {
+        self.output.push_str(v, LengthPrefix::U16)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), CerealError> {
+        self.output.push_blob(v, LengthPrefix::U16)
+    }
+
+    fn serialize_none(self) -> Result<(), CerealError> {
+        self.output.push_u8(0)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), CerealError> {
+        self.output.push_u8(1)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), CerealError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), CerealError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), CerealError> {
+        self.push_variant(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), CerealError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), CerealError> {
+        self.push_variant(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Prefixed<'a, 'b>, CerealError> {
+        Ok(Prefixed::new(self))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, CerealError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, CerealError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, CerealError> {
+        self.push_variant(variant_index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Prefixed<'a, 'b>, CerealError> {
+        Ok(Prefixed::new(self))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, CerealError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, CerealError> {
+        self.push_variant(variant_index)?;
+        Ok(self)
+    }
+}
+
+impl Serializer<'_> {
+    fn push_variant(&mut self, variant_index: u32) -> Result<(), CerealError> {
+        let index = u8::try_from(variant_index).map_err(|_| CerealError::LengthOverflow {
+            len: variant_index as usize,
+            max: u8::MAX as usize,
+        })?;
+        self.output.push_u8(index)
+    }
+}
+
+/// Collects the elements of a sequence or map so they can be pushed
+/// behind their length in bytes.
+pub struct Prefixed<'a, 'b> {
+    parent: &'b mut Serializer<'a>,
+    items: CerealStream,
+}
+
+impl<'a, 'b> Prefixed<'a, 'b> {
+    fn new(parent: &'b mut Serializer<'a>) -> Self {
+        let items = CerealStream::with_endian(parent.output.endian());
+        Self { parent, items }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CerealError> {
+        to_stream(value, &mut self.items)
+    }
+
+    fn finish(self) -> Result<(), CerealError> {
+        self.parent.output.push_blob(self.items.get_vec(), LengthPrefix::U16)
+    }
+}
+
+impl ser::SerializeSeq for Prefixed<'_, '_> {
+    type Ok = ();
+    type Error = CerealError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CerealError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), CerealError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeMap for Prefixed<'_, '_> {
+    type Ok = ();
+    type Error = CerealError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), CerealError> {
+        self.push(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CerealError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), CerealError> {
+        self.finish()
+    }
+}
+
+/// Implements the serde traits that pour fields out one after another.
+macro_rules! in_order {
+    ($($tr:ident :: $method:ident),*) => {
+        $(
+            impl ser::$tr for &mut Serializer<'_> {
+                type Ok = ();
+                type Error = CerealError;
+
+                fn $method<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CerealError> {
+                    value.serialize(&mut **self)
+                }
+
+                fn end(self) -> Result<(), CerealError> {
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+in_order!(
+    SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field,
+    SerializeTupleVariant::serialize_field
+);
+
+impl ser::SerializeStruct for &mut Serializer<'_> {
+    type Ok = ();
+    type Error = CerealError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<(), CerealError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), CerealError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut Serializer<'_> {
+    type Ok = ();
+    type Error = CerealError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<(), CerealError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), CerealError> {
+        Ok(())
+    }
+}
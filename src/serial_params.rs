@@ -1,3 +1,9 @@
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+use super::bits::{BitOrder, BitReader};
+use super::cereal::{CerealReader, CerealStream};
+
 /// A representation of a Serial Port character length
 /// (independent of parity bits)  setting
 ///
@@ -17,8 +23,8 @@
 ///   let v = cl.get_byte();
 ///   let v2 = CharLength::from_byte(&v);
 ///   assert_eq!(Some(cl), v2);
-
-/// ````
+///
+/// ```
 ///
 #[derive(Debug, PartialEq, Clone, Copy, Default)]
 #[repr(u8)]
@@ -164,3 +170,90 @@ impl StopBits {
         }
     }
 }
+
+
+/// Implements serde's `Serialize` and `Deserialize` for the serial
+/// setting enums through their single byte representation, the same byte
+/// the hand written boxes put on the wire.
+macro_rules! byte_enum_serde {
+    ($($ty:ident),*) => {
+        $(
+            impl Serialize for $ty {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.serialize_u8(self.get_byte())
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $ty {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let byte = u8::deserialize(deserializer)?;
+                    $ty::from_byte(&byte).ok_or_else(|| {
+                        de::Error::custom(format!("invalid {} value {}", stringify!($ty), byte))
+                    })
+                }
+            }
+        )*
+    };
+}
+
+byte_enum_serde!(CharLength, Parity, StopBits);
+
+
+/// The character length, parity and stop bits of a Serial Port packed
+/// into a single byte.
+///
+/// From the most significant bit down the byte holds one bit of
+/// character length (0 for eight, 1 for seven), two bits of [`Parity`],
+/// two bits of [`StopBits`] and three bits of zero padding.
+///
+/// # Examples
+///
+/// ```
+///   use open_channel::serial_params::{CharLength, LineSettings, Parity, StopBits};
+///
+///   let ls = LineSettings {
+///       char_len: CharLength::Seven,
+///       parity: Parity::Odd,
+///       stop: StopBits::Two,
+///   };
+///   let v = ls.get_byte();
+///   assert_eq!(v, 0b1101_0000);
+///   assert_eq!(Some(ls), LineSettings::from_byte(&v));
+///
+///   assert_eq!(None, LineSettings::from_byte(&0b0110_0000));
+/// ```
+///
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct LineSettings {
+    pub char_len: CharLength,
+    pub parity: Parity,
+    pub stop: StopBits,
+}
+
+impl LineSettings {
+    /// turn a u8 value to Some(LineSettings).
+    /// returns None if any of the fields has no mapping
+    pub fn from_byte(byte: &u8) -> Option<Self> {
+        let mut source = CerealReader::new(std::slice::from_ref(byte));
+        let mut bits = BitReader::new(&mut source, BitOrder::MsbFirst);
+        let char_len = match bits.read_bits(1).ok()? {
+            0 => CharLength::Eight,
+            _ => CharLength::Seven,
+        };
+        let parity = Parity::from_byte(&(bits.read_bits(2).ok()? as u8))?;
+        let stop = StopBits::from_byte(&(bits.read_bits(2).ok()? as u8))?;
+        Some(Self { char_len, parity, stop })
+    }
+
+    /// Returns the u8 representation of this [`LineSettings`].
+    pub fn get_byte(&self) -> u8 {
+        let mut stream = CerealStream::new();
+        let mut bits = stream.bit_writer(BitOrder::MsbFirst);
+        // an unbounded stream always has room for the byte
+        let _ = bits.write_bool(self.char_len == CharLength::Seven);
+        let _ = bits.write_bits(self.parity.get_byte() as u32, 2);
+        let _ = bits.write_bits(self.stop.get_byte() as u32, 2);
+        let _ = bits.finish();
+        stream.pop_byte()
+    }
+}